@@ -1,61 +1,145 @@
 use anchor_lang::prelude::*;
-use num_derive::FromPrimitive;
-use solana_program::{
-    program_error::ProgramError,
-    decode_error::DecodeError,
-};
-use thiserror::Error;
-
-#[derive(Error, Debug, Clone, FromPrimitive)]
+
+#[error_code]
 pub enum EarthquakeInsuranceError {
-    #[error("Invalid Switchboard feed")]
+    #[msg("Invalid Switchboard feed")]
     InvalidFeed,
     
-    #[error("Invalid Switchboard data")]
+    #[msg("Invalid Switchboard data")]
     InvalidOracleData,
     
-    #[error("Earthquake magnitude is below threshold")]
+    #[msg("Earthquake magnitude is below threshold")]
     MagnitudeBelowThreshold,
 
-    #[error("User location not registered")]
+    #[msg("User location not registered")]
     UserLocationNotRegistered,
 
-    #[error("Region not supported")]
+    #[msg("Region not supported")]
     RegionNotSupported,
 
-    #[error("Invalid instruction")]
+    #[msg("Invalid instruction")]
     InvalidInstruction,
 
-    #[error("Destination not authorized for claim")]
+    #[msg("Destination not authorized for claim")]
     UnauthorizedClaim,
 
-    #[error("Invalid user account data")]
+    #[msg("Invalid user account data")]
     InvalidUserData,
 
-    #[error("Insurance claim already processed")]
+    #[msg("Insurance claim already processed")]
     ClaimAlreadyProcessed,
     
-    #[error("Insurance policy expired")]
+    #[msg("Insurance policy expired")]
     PolicyExpired,
     
-    #[error("Insurance policy not active yet")]
+    #[msg("Insurance policy not active yet")]
     PolicyNotActive,
     
-    #[error("Claim amount exceeds insured amount")]
+    #[msg("Claim amount exceeds insured amount")]
     ExcessClaimAmount,
     
-    #[error("Premium payment insufficient")]
+    #[msg("Premium payment insufficient")]
     InsufficientPremium,
-}
 
-impl From<EarthquakeInsuranceError> for ProgramError {
-    fn from(e: EarthquakeInsuranceError) -> Self {
-        ProgramError::Custom(e as u32)
-    }
-}
+    #[msg("Invalid payout schedule")]
+    InvalidPayoutSchedule,
+
+    #[msg("Disaster event does not match the insured region")]
+    InvalidDisasterEvent,
+
+    #[msg("Disaster event is outside the policy coverage window")]
+    EventOutsideCoverage,
+
+    #[msg("Switchboard feed data is stale")]
+    StaleOracleData,
+
+    #[msg("Disaster event already recorded for this oracle round")]
+    EventAlreadyRecorded,
+
+    #[msg("Coverage zone is retired")]
+    ZoneNotActive,
+
+    #[msg("Invalid coverage zone configuration")]
+    InvalidZoneConfig,
+
+    #[msg("Invalid latitude or longitude")]
+    InvalidCoordinates,
+
+    #[msg("Claimer does not hold the policy token")]
+    PolicyTokenNotHeld,
+
+    #[msg("Policy is still active and not fully claimed")]
+    PolicyStillActive,
+
+    #[msg("Policy cannot be cancelled after a qualifying disaster event")]
+    CancellationBlocked,
+
+    #[msg("Insurance vault has insufficient funds")]
+    InsufficientVaultFunds,
+
+    #[msg("Cancellation fee exceeds 10000 basis points")]
+    InvalidCancellationFee,
+
+    #[msg("Policy cannot be changed after a qualifying disaster event")]
+    PolicyUpdateBlocked,
+
+    #[msg("Invalid policy update")]
+    InvalidPolicyUpdate,
+
+    #[msg("Invalid tranche configuration")]
+    InvalidTrancheConfig,
 
-impl<T> DecodeError<T> for EarthquakeInsuranceError {
-    fn type_of() -> &'static str {
-        "EarthquakeInsuranceError"
-    }
-} 
\ No newline at end of file
+    #[msg("Policy already has a claim awaiting settlement")]
+    ClaimPending,
+
+    #[msg("Policy has no claim awaiting settlement")]
+    NoPendingClaim,
+
+    #[msg("Challenge window for this claim has closed")]
+    ChallengeWindowClosed,
+
+    #[msg("Challenge window for this claim is still open")]
+    ChallengeWindowOpen,
+
+    #[msg("Claim is under dispute")]
+    ClaimDisputed,
+
+    #[msg("Invalid dispute configuration")]
+    InvalidDisputeConfig,
+
+    #[msg("Invalid parameter change")]
+    InvalidParameterChange,
+
+    #[msg("Parameter change is still timelocked")]
+    TimelockNotExpired,
+
+    #[msg("Magnitude has more precision than hundredths or is out of range")]
+    InvalidMagnitude,
+
+    #[msg("Mint does not use this program as its transfer hook")]
+    InvalidHookProgram,
+
+    #[msg("Signer is not the mint's transfer hook authority")]
+    UnauthorizedHookAuthority,
+
+    #[msg("Extra account meta list already exists")]
+    ExtraAccountMetaListExists,
+
+    #[msg("Only the program upgrade authority can initialize the config")]
+    UnauthorizedInitializer,
+
+    #[msg("Transfer hook was not invoked by a Token-2022 transfer of this mint")]
+    NotTransferring,
+
+    #[msg("Transfers signed by a delegate cannot make claims")]
+    DelegateTransferNotAllowed,
+
+    #[msg("Claims against this disaster event were rejected by an upheld dispute")]
+    ClaimEventRejected,
+
+    #[msg("Policy duration must be a positive number of days")]
+    InvalidPolicyDuration,
+
+    #[msg("Tranche has already withdrawn the premiums a refund would return")]
+    PremiumsAlreadyWithdrawn,
+}
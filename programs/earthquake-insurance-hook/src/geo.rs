@@ -0,0 +1,227 @@
+//! Integer-only distance and attenuation math for epicenter-based payouts.
+//!
+//! Coordinates are fixed-point microdegrees (degrees * 1_000_000) and
+//! magnitudes are scaled by 100, matching the rest of the program.
+
+/// Microdegrees in one degree.
+pub const MICRODEGREES_PER_DEGREE: i64 = 1_000_000;
+
+/// Metres per degree of arc on a 6371 km sphere.
+const METRES_PER_DEGREE: i64 = 111_195;
+
+/// Cosine of each whole degree from 0 to 90, scaled by 10_000.
+const COS_TABLE: [i64; 91] = [
+    10000, 9998, 9994, 9986, 9976, 9962, 9945, 9925, 9903, 9877,
+    9848, 9816, 9781, 9744, 9703, 9659, 9613, 9563, 9511, 9455,
+    9397, 9336, 9272, 9205, 9135, 9063, 8988, 8910, 8829, 8746,
+    8660, 8572, 8480, 8387, 8290, 8192, 8090, 7986, 7880, 7771,
+    7660, 7547, 7431, 7314, 7193, 7071, 6947, 6820, 6691, 6561,
+    6428, 6293, 6157, 6018, 5878, 5736, 5592, 5446, 5299, 5150,
+    5000, 4848, 4695, 4540, 4384, 4226, 4067, 3907, 3746, 3584,
+    3420, 3256, 3090, 2924, 2756, 2588, 2419, 2250, 2079, 1908,
+    1736, 1564, 1392, 1219, 1045, 872, 698, 523, 349, 175,
+    0,
+];
+
+/// Latitude beyond which distances are measured on a flat projection centred
+/// on the pole, where the equirectangular one stretches east-west distances.
+const POLAR_LATITUDE: i64 = 80 * MICRODEGREES_PER_DEGREE;
+
+/// Magnitude-distance intensity table: `(max hypocentral distance in km,
+/// magnitude reduction scaled by 100)`. Beyond the last band the quake is
+/// treated as not felt at the insured location.
+pub const ATTENUATION_TABLE: [(u64, u64); 6] = [
+    (25, 0),
+    (50, 25),
+    (100, 50),
+    (200, 100),
+    (400, 150),
+    (800, 250),
+];
+
+pub fn is_valid_coordinate(latitude: i32, longitude: i32) -> bool {
+    (latitude as i64).abs() <= 90 * MICRODEGREES_PER_DEGREE
+        && (longitude as i64).abs() <= 180 * MICRODEGREES_PER_DEGREE
+}
+
+/// Cosine of a latitude in microdegrees, scaled by 10_000.
+fn cos_microdegrees(latitude: i64) -> i64 {
+    let latitude = latitude.abs().min(90 * MICRODEGREES_PER_DEGREE);
+    let degree = (latitude / MICRODEGREES_PER_DEGREE) as usize;
+    let fraction = latitude % MICRODEGREES_PER_DEGREE;
+
+    if degree >= 90 {
+        return COS_TABLE[90];
+    }
+
+    // Linear interpolation between whole degrees
+    let lower = COS_TABLE[degree];
+    let upper = COS_TABLE[degree + 1];
+    lower - (lower - upper) * fraction / MICRODEGREES_PER_DEGREE
+}
+
+/// Cosine of an angle between 0 and 180 degrees in microdegrees, scaled by 10_000.
+fn cos_angle_microdegrees(angle: i64) -> i64 {
+    if angle <= 90 * MICRODEGREES_PER_DEGREE {
+        cos_microdegrees(angle)
+    } else {
+        -cos_microdegrees(180 * MICRODEGREES_PER_DEGREE - angle)
+    }
+}
+
+fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    let mut x = value;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+/// Surface distance in km between two points, using an equirectangular
+/// approximation that stays within a few percent over the ranges the
+/// attenuation table covers. Near a pole, points in its hemisphere are
+/// projected around the pole instead so distances across it stay short.
+pub fn surface_distance_km(
+    latitude_a: i32,
+    longitude_a: i32,
+    latitude_b: i32,
+    longitude_b: i32,
+) -> u64 {
+    let delta_latitude = latitude_b as i64 - latitude_a as i64;
+
+    // Take the short way around the antimeridian
+    let full_turn = 360 * MICRODEGREES_PER_DEGREE;
+    let mut delta_longitude = (longitude_b as i64 - longitude_a as i64) % full_turn;
+    if delta_longitude > full_turn / 2 {
+        delta_longitude -= full_turn;
+    } else if delta_longitude < -full_turn / 2 {
+        delta_longitude += full_turn;
+    }
+
+    let same_hemisphere = (latitude_a as i64) * (latitude_b as i64) > 0;
+    let arc_microdegrees = if same_hemisphere
+        && (latitude_a as i64).abs().max((latitude_b as i64).abs()) >= POLAR_LATITUDE
+    {
+        // Law of cosines on the distances from the pole
+        let radius_a = (90 * MICRODEGREES_PER_DEGREE - (latitude_a as i64).abs()) as i128;
+        let radius_b = (90 * MICRODEGREES_PER_DEGREE - (latitude_b as i64).abs()) as i128;
+        let cos_delta = cos_angle_microdegrees(delta_longitude.abs()) as i128;
+        let squared = radius_a * radius_a + radius_b * radius_b - 2 * radius_a * radius_b * cos_delta / 10_000;
+        isqrt(squared.max(0) as u128)
+    } else {
+        let mean_latitude = (latitude_a as i64 + latitude_b as i64) / 2;
+        let x = delta_longitude * cos_microdegrees(mean_latitude) / 10_000;
+        let y = delta_latitude;
+        isqrt((x as i128 * x as i128 + y as i128 * y as i128) as u128)
+    };
+    (arc_microdegrees * METRES_PER_DEGREE as u128 / (MICRODEGREES_PER_DEGREE as u128 * 1000)) as u64
+}
+
+/// Straight-line distance in km from a location to the quake's hypocenter.
+pub fn hypocentral_distance_km(surface_distance_km: u64, depth_km: u32) -> u64 {
+    let surface = surface_distance_km as u128;
+    let depth = depth_km as u128;
+    isqrt(surface * surface + depth * depth) as u64
+}
+
+/// Magnitude felt at `distance_km` from the hypocenter, scaled by 100, or
+/// 0 if the location is outside the attenuation table.
+pub fn attenuated_magnitude(magnitude: u64, distance_km: u64) -> u64 {
+    ATTENUATION_TABLE
+        .iter()
+        .find(|(max_distance_km, _)| distance_km <= *max_distance_km)
+        .map(|(_, reduction)| magnitude.saturating_sub(*reduction))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn microdegrees(degrees: f64) -> i32 {
+        (degrees * MICRODEGREES_PER_DEGREE as f64).round() as i32
+    }
+
+    fn distance_km(latitude_a: f64, longitude_a: f64, latitude_b: f64, longitude_b: f64) -> u64 {
+        surface_distance_km(
+            microdegrees(latitude_a),
+            microdegrees(longitude_a),
+            microdegrees(latitude_b),
+            microdegrees(longitude_b),
+        )
+    }
+
+    #[test]
+    fn city_pairs_match_great_circle_distances() {
+        // San Francisco - Los Angeles, 559 km
+        assert_eq!(distance_km(37.7749, -122.4194, 34.0522, -118.2437), 559);
+        // Tokyo - Osaka, 392 km
+        assert_eq!(distance_km(35.6762, 139.6503, 34.6937, 135.5023), 392);
+        // Lisbon - Porto, 274 km
+        assert_eq!(distance_km(38.7223, -9.1393, 41.1579, -8.6291), 274);
+        // Order doesn't matter
+        assert_eq!(distance_km(34.0522, -118.2437, 37.7749, -122.4194), 559);
+    }
+
+    #[test]
+    fn distances_take_the_short_way_across_the_antimeridian() {
+        // One degree of longitude at 17S, 106 km
+        assert_eq!(distance_km(-17.0, 179.5, -17.0, -179.5), 106);
+        assert_eq!(distance_km(-17.0, -179.5, -17.0, 179.5), 106);
+        assert_eq!(distance_km(-17.0, 0.5, -17.0, -0.5), 106);
+        // Fiji across the antimeridian, 177 km great-circle
+        assert_eq!(distance_km(-17.5, 178.9, -18.2, -179.6), 176);
+        // The same meridian written both ways is no distance at all
+        assert_eq!(distance_km(10.0, 180.0, 10.0, -180.0), 0);
+    }
+
+    #[test]
+    fn distances_near_the_poles_stay_short() {
+        // Every longitude at a pole is the same point
+        assert_eq!(distance_km(90.0, 0.0, 90.0, 120.0), 0);
+        assert_eq!(distance_km(-90.0, -45.0, -90.0, 135.0), 0);
+        // One degree of latitude from the pole, 111 km
+        assert_eq!(distance_km(89.0, 0.0, 90.0, 77.0), 111);
+        // Across the pole, 22 km and 1112 km great-circle
+        assert_eq!(distance_km(89.9, 0.0, 89.9, 180.0), 22);
+        assert_eq!(distance_km(89.0, 0.0, 81.0, 180.0), 1111);
+        // Southern high latitudes, 1004 km great-circle
+        assert_eq!(distance_km(-85.0, 10.0, -84.0, -100.0), 1003);
+        // Either side of the polar projection, 193 km and 422 km great-circle
+        assert_eq!(distance_km(80.0, 0.0, 80.0, 10.0), 193);
+        assert_eq!(distance_km(79.0, 0.0, 79.0, 20.0), 424);
+    }
+
+    #[test]
+    fn hypocentral_distance_includes_depth() {
+        assert_eq!(hypocentral_distance_km(30, 40), 50);
+        assert_eq!(hypocentral_distance_km(25, 0), 25);
+        assert_eq!(hypocentral_distance_km(0, 10), 10);
+    }
+
+    #[test]
+    fn attenuation_bands_end_at_their_maximum_distance() {
+        for (max_distance_km, reduction) in ATTENUATION_TABLE {
+            assert_eq!(attenuated_magnitude(700, max_distance_km), 700 - reduction);
+        }
+        for bands in ATTENUATION_TABLE.windows(2) {
+            let ((max_distance_km, reduction), (_, next_reduction)) = (bands[0], bands[1]);
+            // The first km past a band already falls in the next one
+            assert_eq!(attenuated_magnitude(700, max_distance_km + 1), 700 - next_reduction);
+            assert!(next_reduction > reduction);
+        }
+        assert_eq!(attenuated_magnitude(700, 0), 700);
+        assert_eq!(attenuated_magnitude(700, 26), 675);
+        assert_eq!(attenuated_magnitude(700, 800), 450);
+        // Not felt beyond the last band
+        assert_eq!(attenuated_magnitude(700, 801), 0);
+        // Reductions never wrap below zero
+        assert_eq!(attenuated_magnitude(100, 800), 0);
+    }
+}
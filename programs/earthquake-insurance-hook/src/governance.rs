@@ -0,0 +1,314 @@
+//! Timelocked parameter changes.
+//!
+//! The authority queues a change with `propose_change`; it can be applied with
+//! `execute_change` once the config's timelock delay has passed, and the
+//! guardian can veto it with `cancel_change` until then.
+//!
+//! Changes that create accounts, new coverage zones and manually recorded
+//! disaster events, are queued the same way but consumed by
+//! `create_coverage_zone` and `record_disaster_event` instead.
+
+use anchor_lang::prelude::*;
+
+use crate::error::EarthquakeInsuranceError;
+use crate::{
+    geo, CoverageZone, Epicenter, InsuranceConfig, PayoutTier, BASIS_POINTS_DIVISOR, MAX_MAGNITUDE,
+    MAX_PAYOUT_TIERS, MIN_MAGNITUDE,
+};
+
+/// Serialized size of the largest `ParameterChange` variant, `ZoneEpicenterFeeds`.
+pub const PARAMETER_CHANGE_MAX_SIZE: usize = 1 + 8 + 32 * 3;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum ParameterChange {
+    ZoneFeed {
+        zone_id: u64,
+        feed: Pubkey,
+    },
+    /// Pubkey::default() for all three turns epicenter reporting off
+    ZoneEpicenterFeeds {
+        zone_id: u64,
+        latitude_feed: Pubkey,
+        longitude_feed: Pubkey,
+        depth_feed: Pubkey,
+    },
+    /// Magnitude scaled by 100
+    ZoneThreshold {
+        zone_id: u64,
+        threshold_magnitude: u64,
+    },
+    ZonePricing {
+        zone_id: u64,
+        premium_rate_bps: u16,
+    },
+    ZoneCoveragePeriods {
+        zone_id: u64,
+        waiting_period_seconds: i64,
+        grace_period_seconds: i64,
+    },
+    PayoutSchedule {
+        tiers: Vec<PayoutTier>,
+    },
+    CancellationFee {
+        cancellation_fee_bps: u16,
+    },
+    DisputeSettings {
+        challenge_window_seconds: i64,
+        dispute_bond: u64,
+        arbiter: Pubkey,
+    },
+    Authority {
+        new_authority: Pubkey,
+    },
+    Guardian {
+        new_guardian: Pubkey,
+    },
+    TimelockDelay {
+        timelock_delay_seconds: i64,
+    },
+    DelegateTransfers {
+        allow_delegate_transfers: bool,
+    },
+    /// Executed by `create_coverage_zone`; magnitude scaled by 100
+    NewCoverageZone {
+        zone_id: u64,
+        feed: Pubkey,
+        threshold_magnitude: u64,
+        premium_rate_bps: u16,
+        waiting_period_seconds: i64,
+        grace_period_seconds: i64,
+    },
+    /// Executed by `record_disaster_event`; magnitude scaled by 100
+    ManualDisasterEvent {
+        zone_id: u64,
+        magnitude: u64,
+        epicenter: Option<Epicenter>,
+    },
+}
+
+impl ParameterChange {
+    /// Zone the change applies to, if any.
+    pub fn zone_id(&self) -> Option<u64> {
+        match self {
+            ParameterChange::ZoneFeed { zone_id, .. }
+            | ParameterChange::ZoneEpicenterFeeds { zone_id, .. }
+            | ParameterChange::ZoneThreshold { zone_id, .. }
+            | ParameterChange::ZonePricing { zone_id, .. }
+            | ParameterChange::ZoneCoveragePeriods { zone_id, .. } => Some(*zone_id),
+            _ => None,
+        }
+    }
+
+    /// Rejects values that could never be applied, so bad proposals fail up front.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ParameterChange::ZoneThreshold { threshold_magnitude, .. } => validate_magnitude(*threshold_magnitude)?,
+            ParameterChange::ZonePricing { premium_rate_bps, .. } => validate_premium_rate(*premium_rate_bps)?,
+            ParameterChange::ZoneCoveragePeriods { waiting_period_seconds, grace_period_seconds, .. } => {
+                validate_coverage_periods(*waiting_period_seconds, *grace_period_seconds)?;
+            }
+            ParameterChange::NewCoverageZone {
+                threshold_magnitude,
+                premium_rate_bps,
+                waiting_period_seconds,
+                grace_period_seconds,
+                ..
+            } => {
+                validate_magnitude(*threshold_magnitude)?;
+                validate_premium_rate(*premium_rate_bps)?;
+                validate_coverage_periods(*waiting_period_seconds, *grace_period_seconds)?;
+            }
+            ParameterChange::ManualDisasterEvent { magnitude, epicenter, .. } => {
+                validate_magnitude(*magnitude)?;
+                if let Some(epicenter) = epicenter {
+                    if !geo::is_valid_coordinate(epicenter.latitude, epicenter.longitude) {
+                        return Err(error!(EarthquakeInsuranceError::InvalidCoordinates));
+                    }
+                }
+            }
+            ParameterChange::PayoutSchedule { tiers } => validate_payout_schedule(tiers)?,
+            ParameterChange::CancellationFee { cancellation_fee_bps } => {
+                if *cancellation_fee_bps > BASIS_POINTS_DIVISOR {
+                    return Err(error!(EarthquakeInsuranceError::InvalidCancellationFee));
+                }
+            }
+            ParameterChange::DisputeSettings { challenge_window_seconds, .. } => {
+                if *challenge_window_seconds <= 0 {
+                    return Err(error!(EarthquakeInsuranceError::InvalidDisputeConfig));
+                }
+            }
+            ParameterChange::TimelockDelay { timelock_delay_seconds } => {
+                if *timelock_delay_seconds <= 0 {
+                    return Err(error!(EarthquakeInsuranceError::InvalidParameterChange));
+                }
+            }
+            ParameterChange::ZoneFeed { .. }
+            | ParameterChange::ZoneEpicenterFeeds { .. }
+            | ParameterChange::Authority { .. }
+            | ParameterChange::Guardian { .. }
+            | ParameterChange::DelegateTransfers { .. } => {}
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, config: &mut InsuranceConfig, zone: Option<&mut CoverageZone>) -> Result<()> {
+        if let Some(zone_id) = self.zone_id() {
+            let zone = match zone {
+                Some(zone) if zone.zone_id == zone_id => zone,
+                _ => {
+                    msg!("Change requires coverage zone {}", zone_id);
+                    return Err(error!(EarthquakeInsuranceError::InvalidZoneConfig));
+                }
+            };
+            apply_to_zone(self, zone);
+            return Ok(());
+        }
+
+        match self {
+            ParameterChange::PayoutSchedule { tiers } => {
+                config.payout_tiers = [PayoutTier::default(); MAX_PAYOUT_TIERS];
+                config.payout_tiers[..tiers.len()].copy_from_slice(tiers);
+                config.payout_tier_count = tiers.len() as u8;
+            }
+            ParameterChange::CancellationFee { cancellation_fee_bps } => {
+                config.cancellation_fee_bps = *cancellation_fee_bps;
+            }
+            ParameterChange::DisputeSettings { challenge_window_seconds, dispute_bond, arbiter } => {
+                config.challenge_window_seconds = *challenge_window_seconds;
+                config.dispute_bond = *dispute_bond;
+                config.arbiter = *arbiter;
+            }
+            ParameterChange::Authority { new_authority } => config.authority = *new_authority,
+            ParameterChange::Guardian { new_guardian } => config.guardian = *new_guardian,
+            ParameterChange::TimelockDelay { timelock_delay_seconds } => {
+                config.timelock_delay_seconds = *timelock_delay_seconds;
+            }
+            ParameterChange::DelegateTransfers { allow_delegate_transfers } => {
+                config.allow_delegate_transfers = *allow_delegate_transfers;
+            }
+            ParameterChange::NewCoverageZone { .. } | ParameterChange::ManualDisasterEvent { .. } => {
+                msg!("Change is executed by create_coverage_zone or record_disaster_event");
+                return Err(error!(EarthquakeInsuranceError::InvalidParameterChange));
+            }
+            _ => unreachable!("zone changes are applied above"),
+        }
+        Ok(())
+    }
+}
+
+fn apply_to_zone(change: &ParameterChange, zone: &mut CoverageZone) {
+    match change {
+        ParameterChange::ZoneFeed { feed, .. } => zone.feed = *feed,
+        ParameterChange::ZoneEpicenterFeeds { latitude_feed, longitude_feed, depth_feed, .. } => {
+            zone.latitude_feed = *latitude_feed;
+            zone.longitude_feed = *longitude_feed;
+            zone.depth_feed = *depth_feed;
+        }
+        ParameterChange::ZoneThreshold { threshold_magnitude, .. } => {
+            zone.threshold_magnitude = *threshold_magnitude;
+        }
+        ParameterChange::ZonePricing { premium_rate_bps, .. } => zone.premium_rate_bps = *premium_rate_bps,
+        // Applies to policies registered or updated from now on, and to grace
+        // periods of existing policies
+        ParameterChange::ZoneCoveragePeriods { waiting_period_seconds, grace_period_seconds, .. } => {
+            zone.waiting_period_seconds = *waiting_period_seconds;
+            zone.grace_period_seconds = *grace_period_seconds;
+        }
+        _ => {}
+    }
+}
+
+fn validate_magnitude(magnitude: u64) -> Result<()> {
+    if !(MIN_MAGNITUDE..=MAX_MAGNITUDE).contains(&magnitude) {
+        return Err(error!(EarthquakeInsuranceError::InvalidMagnitude));
+    }
+    Ok(())
+}
+
+fn validate_premium_rate(premium_rate_bps: u16) -> Result<()> {
+    if premium_rate_bps == 0 || premium_rate_bps > BASIS_POINTS_DIVISOR {
+        return Err(error!(EarthquakeInsuranceError::InvalidZoneConfig));
+    }
+    Ok(())
+}
+
+fn validate_coverage_periods(waiting_period_seconds: i64, grace_period_seconds: i64) -> Result<()> {
+    if waiting_period_seconds < 0 || grace_period_seconds < 0 {
+        return Err(error!(EarthquakeInsuranceError::InvalidZoneConfig));
+    }
+    Ok(())
+}
+
+/// Tiers must be sorted by magnitude and pay out more for larger quakes.
+pub fn validate_payout_schedule(tiers: &[PayoutTier]) -> Result<()> {
+    if tiers.is_empty() || tiers.len() > MAX_PAYOUT_TIERS {
+        return Err(error!(EarthquakeInsuranceError::InvalidPayoutSchedule));
+    }
+
+    for (i, tier) in tiers.iter().enumerate() {
+        if tier.payout_bps == 0 || tier.payout_bps > BASIS_POINTS_DIVISOR {
+            return Err(error!(EarthquakeInsuranceError::InvalidPayoutSchedule));
+        }
+        if i > 0 {
+            let previous = &tiers[i - 1];
+            if tier.magnitude <= previous.magnitude || tier.payout_bps < previous.payout_bps {
+                return Err(error!(EarthquakeInsuranceError::InvalidPayoutSchedule));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A queued change, keyed by its sequence number in the config.
+#[account]
+pub struct PendingChange {
+    pub change_id: u64,
+    pub proposer: Pubkey,
+    /// Earliest time the change can be executed
+    pub eta: i64,
+    pub change: ParameterChange,
+    pub bump: u8,
+}
+
+impl PendingChange {
+    pub fn check_timelock(&self, now: i64) -> Result<()> {
+        if now < self.eta {
+            msg!("Change {} is timelocked until {}", self.change_id, self.eta);
+            return Err(error!(EarthquakeInsuranceError::TimelockNotExpired));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(magnitude: u64, payout_bps: u16) -> PayoutTier {
+        PayoutTier { magnitude, payout_bps }
+    }
+
+    #[test]
+    fn payout_schedules_must_increase_with_magnitude() {
+        assert!(validate_payout_schedule(&[tier(600, 2_500), tier(700, 6_000), tier(800, 10_000)]).is_ok());
+        // Equal payouts for a larger quake are allowed
+        assert!(validate_payout_schedule(&[tier(600, 5_000), tier(700, 5_000)]).is_ok());
+
+        // Magnitudes out of order or repeated
+        assert!(validate_payout_schedule(&[tier(700, 2_500), tier(600, 6_000)]).is_err());
+        assert!(validate_payout_schedule(&[tier(600, 2_500), tier(600, 6_000)]).is_err());
+        // A larger quake paying less
+        assert!(validate_payout_schedule(&[tier(600, 6_000), tier(700, 2_500)]).is_err());
+    }
+
+    #[test]
+    fn payout_schedules_must_fit_the_config() {
+        assert!(validate_payout_schedule(&[]).is_err());
+        assert!(validate_payout_schedule(&[tier(600, 0)]).is_err());
+        assert!(validate_payout_schedule(&[tier(600, BASIS_POINTS_DIVISOR + 1)]).is_err());
+
+        let tiers: Vec<_> = (0..=MAX_PAYOUT_TIERS as u64).map(|i| tier(200 + i * 50, 10_000)).collect();
+        assert!(validate_payout_schedule(&tiers[..MAX_PAYOUT_TIERS]).is_ok());
+        assert!(validate_payout_schedule(&tiers).is_err());
+    }
+}
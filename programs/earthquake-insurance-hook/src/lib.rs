@@ -1,17 +1,36 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::account_info::AccountInfo;
-use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::solana_program::pubkey::Pubkey;
 use anchor_lang::solana_program::sysvar::{Sysvar, rent::Rent};
 use anchor_lang::solana_program::system_instruction;
-use spl_transfer_hook_interface::instruction::TransferHookInstruction;
-use switchboard_v2::{AggregatorAccountData, SwitchboardDecimal};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{
+        transfer_hook::{TransferHook as TransferHookExtension, TransferHookAccount},
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    instruction::AuthorityType,
+    state::{Account as TokenAccountState, Mint as MintState},
+};
+use anchor_spl::token_interface::{
+    self,
+    spl_pod::optional_keys::OptionalNonZeroPubkey,
+    spl_token_metadata_interface::state::{Field, TokenMetadata},
+    Burn, Mint, MintTo, SetAuthority, Token2022, TokenAccount, TokenMetadataInitialize,
+    TokenMetadataUpdateField,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+use switchboard_solana::{AggregatorAccountData, SwitchboardDecimal};
 
 pub mod error;
-pub mod instruction;
+pub mod geo;
+pub mod governance;
 
 use error::EarthquakeInsuranceError;
-use instruction::{InsuranceInstruction, Region};
+use governance::{ParameterChange, PendingChange, PARAMETER_CHANGE_MAX_SIZE};
 
 declare_id!("Eq5YbT6NWnB44SRaxF1PkNamVdfTPkvieYRyuRQXiXMn");
 
@@ -19,240 +38,615 @@ declare_id!("Eq5YbT6NWnB44SRaxF1PkNamVdfTPkvieYRyuRQXiXMn");
 pub mod earthquake_insurance_hook {
     use super::*;
 
-    pub fn initialize_config(ctx: Context<InitializeConfig>, threshold_magnitude: f64) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        max_feed_staleness_seconds: i64,
+        cancellation_fee_bps: u16,
+        challenge_window_seconds: i64,
+        dispute_bond: u64,
+        arbiter: Pubkey,
+        guardian: Pubkey,
+        timelock_delay_seconds: i64,
+        allow_delegate_transfers: bool
+    ) -> Result<()> {
         let config = &mut ctx.accounts.config;
-        
-     
-        if threshold_magnitude < 2.0 || threshold_magnitude > 9.0 {
+
+        if max_feed_staleness_seconds <= 0 {
             return Err(error!(EarthquakeInsuranceError::InvalidOracleData));
         }
+
+        if cancellation_fee_bps > BASIS_POINTS_DIVISOR {
+            return Err(error!(EarthquakeInsuranceError::InvalidCancellationFee));
+        }
+
+        if challenge_window_seconds <= 0 {
+            return Err(error!(EarthquakeInsuranceError::InvalidDisputeConfig));
+        }
+
+        if timelock_delay_seconds <= 0 {
+            return Err(error!(EarthquakeInsuranceError::InvalidParameterChange));
+        }
         
      
-        config.threshold_magnitude = (threshold_magnitude * 100.0) as u64;
         config.authority = ctx.accounts.authority.key();
-        
-        msg!("Earthquake insurance config initialized with threshold: {}", threshold_magnitude);
+        config.max_feed_staleness_seconds = max_feed_staleness_seconds;
+        config.cancellation_fee_bps = cancellation_fee_bps;
+        config.challenge_window_seconds = challenge_window_seconds;
+        config.dispute_bond = dispute_bond;
+        config.arbiter = arbiter;
+        config.guardian = guardian;
+        config.timelock_delay_seconds = timelock_delay_seconds;
+        config.change_count = 0;
+        config.allow_delegate_transfers = allow_delegate_transfers;
+
+        let vault = &mut ctx.accounts.vault;
+        vault.bump = ctx.bumps.vault;
+        vault.undistributed_premiums = 0;
+        vault.total_losses = 0;
+
+        // Until a schedule is set, pay the full insured amount for any quake
+        // that clears its zone's threshold
+        config.payout_tiers = [PayoutTier::default(); MAX_PAYOUT_TIERS];
+        config.payout_tiers[0] = PayoutTier {
+            magnitude: MIN_MAGNITUDE,
+            payout_bps: BASIS_POINTS_DIVISOR,
+        };
+        config.payout_tier_count = 1;
+
+        msg!("Earthquake insurance config initialized by {}", config.authority);
+        msg!("Max feed staleness: {} seconds", max_feed_staleness_seconds);
+        msg!("Cancellation fee: {} bps", cancellation_fee_bps);
+        msg!("Challenge window: {} seconds, Dispute bond: {}, Arbiter: {}",
+            challenge_window_seconds, dispute_bond, arbiter);
+        msg!("Guardian: {}, Timelock delay: {} seconds", guardian, timelock_delay_seconds);
+        msg!("Delegate transfers allowed: {}", allow_delegate_transfers);
+        Ok(())
+    }
+
+    /// Queues a parameter change that can be executed once the timelock delay
+    /// has passed.
+    pub fn propose_change(ctx: Context<ProposeChange>, change: ParameterChange) -> Result<()> {
+        change.validate()?;
+
+        let config = &mut ctx.accounts.config;
+        let change_id = config.change_count;
+        let eta = Clock::get()?.unix_timestamp + config.timelock_delay_seconds;
+        config.change_count += 1;
+
+        let pending_change = &mut ctx.accounts.pending_change;
+        pending_change.change_id = change_id;
+        pending_change.proposer = ctx.accounts.authority.key();
+        pending_change.eta = eta;
+        pending_change.change = change;
+        pending_change.bump = ctx.bumps.pending_change;
+
+        msg!("Change {} proposed, executable from {}: {:?}", change_id, eta, pending_change.change);
+        Ok(())
+    }
+
+    pub fn execute_change(ctx: Context<ExecuteChange>, change_id: u64) -> Result<()> {
+        let pending_change = &ctx.accounts.pending_change;
+        pending_change.check_timelock(Clock::get()?.unix_timestamp)?;
+
+        let zone = ctx.accounts.zone.as_deref_mut();
+        pending_change.change.apply(&mut ctx.accounts.config, zone)?;
+
+        msg!("Change {} executed: {:?}", change_id, pending_change.change);
+        Ok(())
+    }
+
+    /// Guardian veto of a queued change.
+    pub fn cancel_change(ctx: Context<CancelChange>, change_id: u64) -> Result<()> {
+        msg!("Change {} cancelled by guardian: {:?}", change_id, ctx.accounts.pending_change.change);
         Ok(())
     }
 
-    pub fn update_region_feed(
-        ctx: Context<UpdateRegionFeed>,
-        region: Region,
-        feed_pubkey: Pubkey
+    /// Creates the coverage zone queued by a `NewCoverageZone` change once its
+    /// timelock has passed. Anyone can execute it and pays the zone's rent.
+    pub fn create_coverage_zone(
+        ctx: Context<CreateCoverageZone>,
+        change_id: u64,
+        zone_id: u64
     ) -> Result<()> {
-        let feeds = &mut ctx.accounts.region_feeds;
-        
-        match region {
-            Region::Northeast => feeds.northeast_feed = feed_pubkey,
-            Region::Southeast => feeds.southeast_feed = feed_pubkey,
-            Region::Midwest => feeds.midwest_feed = feed_pubkey,
-            Region::Southwest => feeds.southwest_feed = feed_pubkey,
-            Region::West => feeds.west_feed = feed_pubkey,
-        }
-        
-        msg!("Updated feed for region {:?}: {}", region, feed_pubkey);
+        let pending_change = &ctx.accounts.pending_change;
+        pending_change.check_timelock(Clock::get()?.unix_timestamp)?;
+
+        let (feed_pubkey, threshold_magnitude, premium_rate_bps, waiting_period_seconds, grace_period_seconds) =
+            match pending_change.change {
+                ParameterChange::NewCoverageZone {
+                    zone_id: change_zone_id,
+                    feed,
+                    threshold_magnitude,
+                    premium_rate_bps,
+                    waiting_period_seconds,
+                    grace_period_seconds,
+                } if change_zone_id == zone_id => {
+                    (feed, threshold_magnitude, premium_rate_bps, waiting_period_seconds, grace_period_seconds)
+                }
+                _ => {
+                    msg!("Change {} does not create coverage zone {}", change_id, zone_id);
+                    return Err(error!(EarthquakeInsuranceError::InvalidParameterChange));
+                }
+            };
+
+        let zone = &mut ctx.accounts.zone;
+        zone.zone_id = zone_id;
+        zone.event_count = 0;
+        zone.feed = feed_pubkey;
+        zone.threshold_magnitude = threshold_magnitude;
+        zone.premium_rate_bps = premium_rate_bps;
+        zone.last_event_round = 0;
+        zone.last_event_timestamp = 0;
+        zone.is_active = true;
+        zone.bump = ctx.bumps.zone;
+        zone.waiting_period_seconds = waiting_period_seconds;
+        zone.grace_period_seconds = grace_period_seconds;
+        zone.above_threshold = false;
+
+        msg!("Coverage zone {} created with feed {}", zone_id, feed_pubkey);
+        msg!("Threshold magnitude: {}, Premium rate: {} bps/year", Magnitude(threshold_magnitude), premium_rate_bps);
+        msg!("Waiting period: {} seconds, Grace period: {} seconds", waiting_period_seconds, grace_period_seconds);
+        Ok(())
+    }
+
+    pub fn retire_coverage_zone(ctx: Context<RetireCoverageZone>, zone_id: u64) -> Result<()> {
+        let zone = &mut ctx.accounts.zone;
+
+        // Existing policies keep their coverage; the zone only stops taking new ones
+        zone.is_active = false;
+
+        msg!("Coverage zone {} retired", zone_id);
         Ok(())
     }
 
     pub fn register_user_location(
         ctx: Context<RegisterUserLocation>,
-        region: Region,
+        zone_id: u64,
         insurance_amount: u64,
         premium: u64,
-        duration_in_days: u64
+        duration_in_days: u64,
+        latitude: i32,
+        longitude: i32
     ) -> Result<()> {
-        let mut user_account_data = ctx.accounts.user_account.load_init()?;
+        let zone = &ctx.accounts.zone;
         let user = &ctx.accounts.user;
+
+        if !zone.is_active {
+            msg!("Coverage zone {} is retired", zone_id);
+            return Err(error!(EarthquakeInsuranceError::ZoneNotActive));
+        }
+
+        if !geo::is_valid_coordinate(latitude, longitude) {
+            return Err(error!(EarthquakeInsuranceError::InvalidCoordinates));
+        }
         
         let clock = Clock::get()?;
         let current_timestamp = clock.unix_timestamp;
-        let end_timestamp = current_timestamp + (duration_in_days as i64 * 24 * 60 * 60);
+        let duration_seconds = policy_duration_seconds(duration_in_days)?;
+        // Coverage starts after the zone's waiting period so it can't be bought as a quake unfolds
+        let start_timestamp = current_timestamp + zone.waiting_period_seconds;
+        let end_timestamp = start_timestamp
+            .checked_add(duration_seconds)
+            .ok_or(error!(EarthquakeInsuranceError::InvalidPolicyDuration))?;
+
+        let required_premium = zone.required_premium(insurance_amount, duration_seconds)?;
+        if premium < required_premium {
+            msg!("Premium {} below required {} for zone {}", premium, required_premium, zone_id);
+            return Err(error!(EarthquakeInsuranceError::InsufficientPremium));
+        }
         
-        user_account_data.owner = user.key();
-        user_account_data.region = region as u8;
-        user_account_data.has_claimed = false;
-        user_account_data.insurance_amount = insurance_amount;
-        user_account_data.premium_paid = premium;
-        user_account_data.policy_start_time = current_timestamp;
-        user_account_data.policy_end_time = end_timestamp;
+        // Premiums are held in the vault that pays refunds
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            premium,
+        )?;
+        ctx.accounts.vault.undistributed_premiums += premium;
+
+        let user_key = user.key();
+        let policy_id = ctx.accounts.user_account.policy_count;
+        {
+            let user_account = &mut ctx.accounts.user_account;
+            user_account.owner = user_key;
+            user_account.policy_count += 1;
+
+            let mut policy_data = ctx.accounts.policy.load_init()?;
+            policy_data.registrant = user_key;
+            policy_data.policy_id = policy_id;
+            policy_data.zone_id = zone_id;
+            policy_data.mint = ctx.accounts.policy_mint.key();
+            policy_data.claimed_amount = 0;
+            policy_data.insurance_amount = insurance_amount;
+            policy_data.premium_paid = premium;
+            policy_data.policy_start_time = start_timestamp;
+            policy_data.policy_end_time = end_timestamp;
+            policy_data.pending_insurance_amount = 0;
+            policy_data.pending_effective_time = 0;
+            policy_data.clear_pending_claim();
+            policy_data.latitude = latitude;
+            policy_data.longitude = longitude;
+        }
+
+        mint_policy_token(
+            ctx.accounts,
+            ctx.bumps.policy_authority,
+            policy_id,
+            zone_id,
+            insurance_amount,
+            end_timestamp,
+        )?;
         
-        msg!("User {} registered in region {:?}", user.key(), region);
-        msg!("Insurance amount: {}, Premium: {}, Validity: {} days", 
-            insurance_amount, premium, duration_in_days);
+        msg!("User {} registered policy {} in zone {} at ({}, {})",
+            user_key, policy_id, zone_id, latitude, longitude);
+        msg!("Insurance amount: {}, Premium: {}, Validity: {} days from {}", 
+            insurance_amount, premium, duration_in_days, start_timestamp);
+        msg!("Policy token minted: {}", ctx.accounts.policy_mint.key());
+        Ok(())
+    }
+
+    pub fn select_claim_policy(
+        ctx: Context<SelectClaimPolicy>,
+        registrant: Pubkey,
+        policy_id: u64
+    ) -> Result<()> {
+        let policy_data = ctx.accounts.policy.load()?;
+        check_policy_holder(&policy_data, &ctx.accounts.policy_token_account, &ctx.accounts.user.key())?;
+
+        // Transfer-hook claims resolve the policy from this selection
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner = ctx.accounts.user.key();
+        user_account.claim_policy_registrant = registrant;
+        user_account.claim_policy_id = policy_id;
+
+        msg!("User {} will claim against policy {} of {}", ctx.accounts.user.key(), policy_id, registrant);
+        Ok(())
+    }
+
+    /// Burns a policy token once the policy has paid out in full or expired.
+    /// Claims run inside a Token-2022 transfer and cannot re-enter it to burn,
+    /// so anyone may crank this afterwards.
+    pub fn burn_policy_token(
+        ctx: Context<BurnPolicyToken>,
+        registrant: Pubkey,
+        policy_id: u64
+    ) -> Result<()> {
+        let policy_data = ctx.accounts.policy.load()?;
+
+        if policy_data.mint != ctx.accounts.policy_mint.key() {
+            return Err(error!(EarthquakeInsuranceError::PolicyTokenNotHeld));
+        }
+
+        if ctx.accounts.zone.zone_id != policy_data.zone_id {
+            return Err(error!(EarthquakeInsuranceError::RegionNotSupported));
+        }
+
+        // Late-reported quakes can still be claimed until the grace period ends
+        let claim_deadline = policy_data.policy_end_time + ctx.accounts.zone.grace_period_seconds;
+        let current_time = Clock::get()?.unix_timestamp;
+        let fully_claimed = policy_data.claimed_amount >= policy_data.latest_insurance_amount();
+        if policy_data.pending_claim_amount > 0 || (!fully_claimed && current_time <= claim_deadline) {
+            msg!("Policy {} of {} still claimable until {}", policy_id, registrant, claim_deadline);
+            return Err(error!(EarthquakeInsuranceError::PolicyStillActive));
+        }
+
+        burn_policy_token_from(
+            &ctx.accounts.token_program,
+            &ctx.accounts.policy_mint,
+            &ctx.accounts.policy_token_account,
+            &ctx.accounts.policy_authority,
+            ctx.bumps.policy_authority,
+        )?;
+
+        msg!("Burned policy token {} for policy {} of {}", policy_data.mint, policy_id, registrant);
+        Ok(())
+    }
+
+    /// Cancels a policy, refunding the unused share of its premium from the
+    /// vault minus the configured cancellation fee.
+    pub fn cancel_policy(
+        ctx: Context<CancelPolicy>,
+        registrant: Pubkey,
+        policy_id: u64
+    ) -> Result<()> {
+        let refund = {
+            let policy_data = ctx.accounts.policy.load()?;
+            let zone = &ctx.accounts.zone;
+
+            check_policy_holder(&policy_data, &ctx.accounts.policy_token_account, &ctx.accounts.user.key())?;
+
+            if policy_data.mint != ctx.accounts.policy_mint.key() {
+                return Err(error!(EarthquakeInsuranceError::PolicyTokenNotHeld));
+            }
+
+            if zone.zone_id != policy_data.zone_id {
+                return Err(error!(EarthquakeInsuranceError::RegionNotSupported));
+            }
+
+            // A quake during coverage may still be claimed, so the premium is earned
+            if policy_data.claimed_amount > 0 || zone.has_event_since(policy_data.policy_start_time) {
+                msg!("Zone {} recorded event #{} at {} during policy coverage",
+                     zone.zone_id, zone.event_count, zone.last_event_timestamp);
+                return Err(error!(EarthquakeInsuranceError::CancellationBlocked));
+            }
+
+            let current_time = Clock::get()?.unix_timestamp;
+            if current_time > policy_data.policy_end_time {
+                return Err(error!(EarthquakeInsuranceError::PolicyExpired));
+            }
+
+            policy_data.cancellation_refund(current_time, ctx.accounts.config.cancellation_fee_bps)
+        };
+
+        pay_from_vault(&ctx.accounts.vault, &ctx.accounts.user.to_account_info(), refund)?;
+
+        let mut junior_tranche = load_tranche(&ctx.accounts.junior_tranche)?;
+        let mut senior_tranche = load_tranche(&ctx.accounts.senior_tranche)?;
+        return_refunded_premiums(
+            &mut ctx.accounts.vault,
+            junior_tranche.as_mut(),
+            senior_tranche.as_mut(),
+            refund,
+        )?;
+        if let Some(junior_tranche) = &junior_tranche {
+            store_tranche(&ctx.accounts.junior_tranche, junior_tranche)?;
+        }
+        if let Some(senior_tranche) = &senior_tranche {
+            store_tranche(&ctx.accounts.senior_tranche, senior_tranche)?;
+        }
+
+        burn_policy_token_from(
+            &ctx.accounts.token_program,
+            &ctx.accounts.policy_mint,
+            &ctx.accounts.policy_token_account,
+            &ctx.accounts.policy_authority,
+            ctx.bumps.policy_authority,
+        )?;
+
+        msg!("Policy {} of {} cancelled, refunded {}", policy_id, registrant, refund);
         Ok(())
     }
 
     pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
-        let (config_pubkey, _) = Pubkey::find_program_address(&[b"config"], ctx.program_id);
-        let (region_feeds_pubkey, _) = Pubkey::find_program_address(&[b"region_feeds"], ctx.program_id);
-        
         msg!("Initializing extra account meta list for mint: {}", ctx.accounts.mint.key());
-        msg!("Config PDA: {}", config_pubkey);
-        msg!("Region feeds PDA: {}", region_feeds_pubkey);
+
+        check_transfer_hook_authority(ctx.program_id, &ctx.accounts.mint, ctx.accounts.authority.key)?;
+
+        // Existing lists can only be changed through update_extra_account_meta_list
+        if ctx.accounts.extra_account_metas.owner == ctx.program_id {
+            return Err(error!(EarthquakeInsuranceError::ExtraAccountMetaListExists));
+        }
+
+        let extra_account_metas = claim_extra_account_metas()?;
+        let account_metas_size = ExtraAccountMetaList::size_of(extra_account_metas.len())?;
         
-     
-        let extra_account_metas = vec![
-            (
-                config_pubkey,
-                false,
-                false,
-            ),
-            (
-                region_feeds_pubkey,
-                false,
-                false,
-            ),
-           
-            (
-                Pubkey::default(), 
-                false,
-                true, 
-            ),
-            (
-                Pubkey::default(), 
-                false,
-                false,
-            ),
+        let mint_key = ctx.accounts.mint.key();
+        let signer_seeds: &[&[u8]] = &[
+            b"extra-account-metas",
+            mint_key.as_ref(),
+            &[ctx.bumps.extra_account_metas],
         ];
         
-        let account_metas_size = 1 + extra_account_metas.len() * 34;
-        
-        if ctx.accounts.extra_account_metas.owner != ctx.program_id {
-            let rent = Rent::get()?;
-            let lamports = rent.minimum_balance(account_metas_size);
-            
-            msg!("Creating account with {} lamports for rent exemption", lamports);
-            invoke(
-                &system_instruction::create_account(
-                    ctx.accounts.authority.key,
-                    ctx.accounts.extra_account_metas.key,
-                    lamports,
-                    account_metas_size as u64,
-                    ctx.program_id,
-                ),
-                &[
-                    ctx.accounts.authority.to_account_info(),
-                    ctx.accounts.extra_account_metas.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-            msg!("Account created successfully");
-        }
+        create_extra_account_meta_list(
+            &ctx.accounts.extra_account_metas,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            ctx.program_id,
+            signer_seeds,
+            account_metas_size,
+        )?;
+        msg!("Account created successfully");
         
         msg!("Initializing extra account meta data");
         let mut data = ctx.accounts.extra_account_metas.try_borrow_mut_data()?;
-        data.fill(0);
-        data[0] = extra_account_metas.len() as u8;
-        
-        let mut offset = 1;
-        for (pubkey, is_signer, is_writable) in extra_account_metas {
-            data[offset..offset+32].copy_from_slice(pubkey.as_ref());
-            data[offset+32] = is_signer as u8;
-            data[offset+33] = is_writable as u8;
-            msg!("Added extra account meta: {}", pubkey);
-            msg!("is_signer: {}, is_writable: {}", is_signer, is_writable);
-            offset += 34;
-        }
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_account_metas)?;
         
-        msg!("Extra account meta list initialized successfully");
+        msg!("Extra account meta list initialized with {} accounts", extra_account_metas.len());
+        Ok(())
+    }
+
+    /// Rewrites an existing list with the accounts the current program expects.
+    pub fn update_extra_account_meta_list(ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+        check_transfer_hook_authority(ctx.program_id, &ctx.accounts.mint, ctx.accounts.authority.key)?;
+
+        // Lists written by older versions of the program are resized in place
+        let extra_account_metas = claim_extra_account_metas()?;
+        let list = &ctx.accounts.extra_account_metas;
+        resize_extra_account_meta_list(
+            list,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ExtraAccountMetaList::size_of(extra_account_metas.len())?,
+        )?;
+
+        let mut data = list.try_borrow_mut_data()?;
+        data.fill(0);
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_account_metas)?;
+
+        msg!("Extra account meta list updated for mint {} with {} accounts",
+             ctx.accounts.mint.key(), extra_account_metas.len());
         Ok(())
     }
 
     pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
         msg!("Transfer hook executing for earthquake insurance claim with amount: {}", amount);
 
-        let user_data = ctx.accounts.user_account.load::<UserAccount>()?;
-        
-        // Kiểm tra người dùng và quyền sở hữu
-        if user_data.owner != ctx.accounts.owner.key() {
-            msg!("User not registered or mismatch");
-            return Err(error!(EarthquakeInsuranceError::UserLocationNotRegistered));
+        check_is_transferring(
+            &ctx.accounts.source.to_account_info(),
+            &ctx.accounts.mint,
+            &ctx.accounts.destination,
+        )?;
+        let holder = resolve_claim_holder(
+            &ctx.accounts.config,
+            &ctx.accounts.source,
+            ctx.accounts.owner.key,
+        )?;
+
+        process_claim(
+            &ctx.accounts.config,
+            &ctx.accounts.zone,
+            &ctx.accounts.policy,
+            &ctx.accounts.disaster_event,
+            &ctx.accounts.policy_token_account,
+            &holder,
+            amount,
+        )
+    }
+
+    pub fn submit_claim(
+        ctx: Context<SubmitClaim>,
+        registrant: Pubkey,
+        policy_id: u64,
+        amount: u64
+    ) -> Result<()> {
+        process_claim(
+            &ctx.accounts.config,
+            &ctx.accounts.zone,
+            &ctx.accounts.policy,
+            &ctx.accounts.disaster_event,
+            &ctx.accounts.policy_token_account,
+            &ctx.accounts.claimant.key(),
+            amount,
+        )?;
+
+        msg!("Claim submitted on policy {} of {}", policy_id, registrant);
+        Ok(())
+    }
+
+    /// Disputes a pending claim during its challenge window, posting the
+    /// configured bond alongside the evidence.
+    pub fn dispute_claim(
+        ctx: Context<DisputeClaim>,
+        registrant: Pubkey,
+        policy_id: u64,
+        evidence_oracle_round: u64,
+        evidence_hash: [u8; 32]
+    ) -> Result<()> {
+        let policy_data = ctx.accounts.policy.load()?;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        if policy_data.pending_claim_amount == 0 {
+            return Err(error!(EarthquakeInsuranceError::NoPendingClaim));
         }
-        
-        // Kiểm tra đã claim chưa
-        if user_data.has_claimed {
-            msg!("User has already claimed insurance");
-            return Err(error!(EarthquakeInsuranceError::ClaimAlreadyProcessed));
+
+        if current_time > policy_data.claim_challenge_deadline {
+            return Err(error!(EarthquakeInsuranceError::ChallengeWindowClosed));
         }
-        
-        // Kiểm tra thời gian hiệu lực
-        let clock = Clock::get()?;
-        let current_time = clock.unix_timestamp;
-        
-        if current_time < user_data.policy_start_time || current_time > user_data.policy_end_time {
-            msg!("Insurance policy expired or not active");
-            return Err(error!(EarthquakeInsuranceError::UnauthorizedClaim));
+
+        let bond = ctx.accounts.config.dispute_bond;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.challenger.to_account_info(),
+                    to: ctx.accounts.dispute.to_account_info(),
+                },
+            ),
+            bond,
+        )?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.policy = ctx.accounts.policy.key();
+        dispute.challenger = ctx.accounts.challenger.key();
+        dispute.claim_amount = policy_data.pending_claim_amount;
+        dispute.bond = bond;
+        dispute.evidence_oracle_round = evidence_oracle_round;
+        dispute.evidence_hash = evidence_hash;
+        dispute.bump = ctx.bumps.dispute;
+
+        msg!("Claim of {} on policy {} of {} disputed by {} citing oracle round {}",
+            dispute.claim_amount, policy_id, registrant, dispute.challenger, evidence_oracle_round);
+        Ok(())
+    }
+
+    /// Settles a dispute. An upheld dispute drops the claim and returns the
+    /// bond; a rejected one forfeits the bond to the pool and lets the claim
+    /// be finalized straight away.
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        registrant: Pubkey,
+        policy_id: u64,
+        claim_valid: bool
+    ) -> Result<()> {
+        let mut policy_data = ctx.accounts.policy.load_mut()?;
+
+        if claim_valid {
+            let bond = ctx.accounts.dispute.bond;
+            **ctx.accounts.dispute.to_account_info().try_borrow_mut_lamports()? -= bond;
+            **ctx.accounts.vault.to_account_info().try_borrow_mut_lamports()? += bond;
+
+            policy_data.claim_challenge_deadline = Clock::get()?.unix_timestamp;
+            msg!("Dispute rejected, claim on policy {} of {} can be finalized", policy_id, registrant);
+        } else {
+            // Keep the event so the same claim can't simply be resubmitted
+            policy_data.rejected_claim_event = policy_data.pending_claim_event;
+            policy_data.clear_pending_claim();
+            msg!("Dispute upheld, claim on policy {} of {} dropped", policy_id, registrant);
         }
-        
-        // Kiểm tra số tiền claim có phù hợp không
-        if amount > user_data.insurance_amount {
-            msg!("Claim amount exceeds insured amount");
+
+        // The dispute account closes to the challenger, returning the bond if it wasn't forfeited
+        Ok(())
+    }
+
+    /// Pays out an undisputed claim after its challenge window and runs the
+    /// loss through the tranche waterfall.
+    pub fn finalize_claim(
+        ctx: Context<FinalizeClaim>,
+        registrant: Pubkey,
+        policy_id: u64
+    ) -> Result<()> {
+        let mut policy_data = ctx.accounts.policy.load_mut()?;
+        let amount = policy_data.pending_claim_amount;
+
+        if amount == 0 {
+            return Err(error!(EarthquakeInsuranceError::NoPendingClaim));
+        }
+
+        if !ctx.accounts.dispute.data_is_empty() {
+            return Err(error!(EarthquakeInsuranceError::ClaimDisputed));
+        }
+
+        if Clock::get()?.unix_timestamp < policy_data.claim_challenge_deadline {
+            return Err(error!(EarthquakeInsuranceError::ChallengeWindowOpen));
+        }
+
+        if ctx.accounts.claimant.key() != policy_data.pending_claim_claimant {
             return Err(error!(EarthquakeInsuranceError::UnauthorizedClaim));
         }
 
-        let config = &ctx.accounts.config;
-        let region_feeds = &ctx.accounts.region_feeds;
-        
+        policy_data.claimed_amount += amount;
+        policy_data.clear_pending_claim();
 
-        let region = match Region::from_u8(user_data.region) {
-            Some(r) => r,
-            None => return Err(error!(EarthquakeInsuranceError::RegionNotSupported)),
-        };
-        
-        let feed_pubkey = match region {
-            Region::Northeast => region_feeds.northeast_feed,
-            Region::Southeast => region_feeds.southeast_feed,
-            Region::Midwest => region_feeds.midwest_feed,
-            Region::Southwest => region_feeds.southwest_feed,
-            Region::West => region_feeds.west_feed,
-        };
-        
-        let switchboard_feed = &ctx.accounts.switchboard_feed;
-            
-     
-        if switchboard_feed.key() != feed_pubkey {
-            msg!("Feed not matching configured feed for region. Expected: {}, Got: {}", 
-                 feed_pubkey, switchboard_feed.key());
-            return Err(error!(EarthquakeInsuranceError::InvalidFeed));
+        pay_from_vault(&ctx.accounts.vault, &ctx.accounts.claimant.to_account_info(), amount)?;
+
+        // Without tranches the pool keeps the whole loss
+        let mut junior_tranche = load_tranche(&ctx.accounts.junior_tranche)?;
+        let mut senior_tranche = load_tranche(&ctx.accounts.senior_tranche)?;
+        absorb_claim_loss(
+            &mut ctx.accounts.vault,
+            junior_tranche.as_mut(),
+            senior_tranche.as_mut(),
+            amount,
+        )?;
+        if let Some(junior_tranche) = &junior_tranche {
+            store_tranche(&ctx.accounts.junior_tranche, junior_tranche)?;
         }
-        
- 
-        let feed_data = match AggregatorAccountData::new(switchboard_feed) {
-            Ok(data) => data,
-            Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
-        };
-        
-        let magnitude_result = match feed_data.get_result() {
-            Ok(result) => result,
-            Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
-        };
-        
-    
-        let magnitude = magnitude_result.mantissa as f64 / 10f64.powi(magnitude_result.scale as i32);
-        let magnitude_scaled = (magnitude * 100.0) as u64;
-        
-        msg!("Region: {:?}, Magnitude from feed: {}", region, magnitude);
-        msg!("Threshold magnitude: {}", config.threshold_magnitude as f64 / 100.0);
-        
-     
-        if magnitude_scaled >= config.threshold_magnitude {
-            msg!("Magnitude {} exceeds threshold {}, allowing insurance claim", 
-                magnitude, config.threshold_magnitude as f64 / 100.0);
-            
-         
-            let mut user_account_data = ctx.accounts.user_account.load_mut::<UserAccount>()?;
-            user_account_data.has_claimed = true;
-            
-            Ok(())
-        } else {
-            msg!("Magnitude {} below threshold {}, rejecting claim", 
-                magnitude, config.threshold_magnitude as f64 / 100.0);
-            Err(error!(EarthquakeInsuranceError::MagnitudeBelowThreshold))
+        if let Some(senior_tranche) = &senior_tranche {
+            store_tranche(&ctx.accounts.senior_tranche, senior_tranche)?;
         }
+
+        msg!("Claim of {} on policy {} of {} paid to {}",
+            amount, policy_id, registrant, ctx.accounts.claimant.key());
+        Ok(())
     }
 
     pub fn fallback<'info>(
-        program_id: &Pubkey,
+        _program_id: &Pubkey,
         accounts: &'info [AccountInfo<'info>],
         data: &[u8],
     ) -> Result<()> {
@@ -270,162 +664,37 @@ pub mod earthquake_insurance_hook {
             TransferHookInstruction::Execute { amount } => {
                 msg!("Execute with amount: {}", amount);
                 
-                if accounts.len() < 9 {
-                    msg!("Not enough accounts provided. Expected at least 9, got {}", accounts.len());
+                if accounts.len() < 12 {
+                    msg!("Not enough accounts provided. Expected at least 12, got {}", accounts.len());
                     return Err(error!(EarthquakeInsuranceError::InvalidInstruction));
                 }
                 
-                let source_account = &accounts[0];
-                let mint_account = &accounts[1];
-                let destination_account = &accounts[2];
+                check_is_transferring(&accounts[0], &accounts[1], &accounts[2])?;
+
                 let owner_account = &accounts[3];
-                let extra_meta_account = &accounts[4];
                 let config_account = &accounts[5];
-                let region_feeds_account = &accounts[6];
-                let user_account = &accounts[7];
-                let switchboard_feed = &accounts[8];
-                
-                // Lấy dữ liệu từ config account
-                let config_data = config_account.try_borrow_data()?;
-                if config_data.len() < 8 + 8 + 32 {
-                    msg!("Invalid config account data");
-                    return Err(error!(EarthquakeInsuranceError::InvalidInstruction));
-                }
-                
-                // Đọc threshold_magnitude (u64 = 8 bytes sau 8 bytes discriminator)
-                let threshold_magnitude = u64::from_le_bytes([
-                    config_data[8], config_data[9], config_data[10], config_data[11],
-                    config_data[12], config_data[13], config_data[14], config_data[15],
-                ]);
-                
-                // Lấy dữ liệu từ user account
-                let user_data = user_account.try_borrow_data()?;
-                if user_data.len() < 8 + 32 + 1 + 1 + 8 + 8 + 8 + 8 {
-                    msg!("Invalid user account data");
-                    return Err(error!(EarthquakeInsuranceError::InvalidUserData));
-                }
-                
-                // Đọc owner, region và has_claimed
-                let mut owner_bytes = [0u8; 32];
-                owner_bytes.copy_from_slice(&user_data[8..40]); // 8 byte discriminator + 32 byte pubkey
-                let owner_pubkey = Pubkey::new_from_array(owner_bytes);
-                
-                if owner_pubkey != *owner_account.key {
-                    msg!("Owner mismatch");
-                    return Err(error!(EarthquakeInsuranceError::UserLocationNotRegistered));
-                }
-                
-                let region_value = user_data[40]; 
-                let has_claimed = user_data[41] != 0; 
-                
-                if has_claimed {
-                    msg!("User has already claimed insurance");
-                    return Err(error!(EarthquakeInsuranceError::ClaimAlreadyProcessed));
-                }
-                
-         
-                let insurance_amount_bytes = &user_data[42..50]; 
-                let insurance_amount = u64::from_le_bytes([
-                    insurance_amount_bytes[0], insurance_amount_bytes[1], insurance_amount_bytes[2], 
-                    insurance_amount_bytes[3], insurance_amount_bytes[4], insurance_amount_bytes[5], 
-                    insurance_amount_bytes[6], insurance_amount_bytes[7]
-                ]);
-                
-               
-                let policy_start_time_bytes = &user_data[58..66]; 
-                let policy_end_time_bytes = &user_data[66..74]; 
-                
-                let policy_start_time = i64::from_le_bytes([
-                    policy_start_time_bytes[0], policy_start_time_bytes[1], policy_start_time_bytes[2], 
-                    policy_start_time_bytes[3], policy_start_time_bytes[4], policy_start_time_bytes[5], 
-                    policy_start_time_bytes[6], policy_start_time_bytes[7]
-                ]);
-                
-                let policy_end_time = i64::from_le_bytes([
-                    policy_end_time_bytes[0], policy_end_time_bytes[1], policy_end_time_bytes[2], 
-                    policy_end_time_bytes[3], policy_end_time_bytes[4], policy_end_time_bytes[5], 
-                    policy_end_time_bytes[6], policy_end_time_bytes[7]
-                ]);
-                
-           
-                let clock = Clock::get()?;
-                let current_time = clock.unix_timestamp;
-                
-                if current_time < policy_start_time {
-                    msg!("Insurance policy not active yet");
-                    return Err(error!(EarthquakeInsuranceError::PolicyNotActive));
-                }
-                
-                if current_time > policy_end_time {
-                    msg!("Insurance policy has expired");
-                    return Err(error!(EarthquakeInsuranceError::PolicyExpired));
-                }
-                
-           
-                if amount > insurance_amount {
-                    msg!("Claim amount exceeds insured amount: {} > {}", amount, insurance_amount);
-                    return Err(error!(EarthquakeInsuranceError::ExcessClaimAmount));
-                }
-                
-                
-                let region_feeds_data = region_feeds_account.try_borrow_data()?;
-                if region_feeds_data.len() < 8 + 32 * 5 {
-                    msg!("Invalid region feeds account data");
-                    return Err(error!(EarthquakeInsuranceError::InvalidInstruction));
-                }
-                
-               
-                let region_offset = 8 + (region_value as usize) * 32;
-                let mut feed_pubkey_bytes = [0u8; 32];
-                feed_pubkey_bytes.copy_from_slice(&region_feeds_data[region_offset..region_offset+32]);
-                let expected_feed_pubkey = Pubkey::new_from_array(feed_pubkey_bytes);
-                
-                if expected_feed_pubkey != *switchboard_feed.key {
-                    msg!("Feed mismatch for region. Expected: {}, Got: {}", 
-                         expected_feed_pubkey, switchboard_feed.key());
-                    return Err(error!(EarthquakeInsuranceError::InvalidFeed));
-                }
-                
-        
-                let feed_data = match AggregatorAccountData::new(switchboard_feed) {
-                    Ok(data) => data,
-                    Err(_) => {
-                        msg!("Failed to read switchboard feed data");
-                        return Err(error!(EarthquakeInsuranceError::InvalidOracleData));
-                    }
-                };
-                
-                let magnitude_result = match feed_data.get_result() {
-                    Ok(result) => result,
-                    Err(_) => {
-                        msg!("Failed to get result from switchboard feed");
-                        return Err(error!(EarthquakeInsuranceError::InvalidOracleData));
-                    }
-                };
-                
-              
-                let magnitude = magnitude_result.mantissa as f64 / 10f64.powi(magnitude_result.scale as i32);
-                let magnitude_scaled = (magnitude * 100.0) as u64;
-                
-                msg!("Magnitude from feed: {}, threshold: {}", 
-                    magnitude, threshold_magnitude as f64 / 100.0);
-                
-                if magnitude_scaled >= threshold_magnitude {
-               
-                    if user_account.is_writable {
-                        let mut user_data = user_account.try_borrow_mut_data()?;
-                        if user_data.len() > 41 {
-                            user_data[41] = 1;
-                        }
-                    }
-                    
-                    msg!("Magnitude {} exceeds threshold, allowing insurance claim for amount {}", 
-                        magnitude, amount);
-                    Ok(())
-                } else {
-                    msg!("Magnitude {} below threshold, rejecting claim", magnitude);
-                    Err(error!(EarthquakeInsuranceError::MagnitudeBelowThreshold))
-                }
+                let policy_account = &accounts[7];
+                let zone_account = &accounts[8];
+                let disaster_event_account = &accounts[9];
+                let policy_token_account = &accounts[11];
+
+                let config = Account::<InsuranceConfig>::try_from(config_account)?;
+                let policy = AccountLoader::<Policy>::try_from(policy_account)?;
+                let zone = Account::<CoverageZone>::try_from(zone_account)?;
+                let disaster_event = Account::<DisasterEvent>::try_from(disaster_event_account)?;
+                let policy_token_account = InterfaceAccount::<TokenAccount>::try_from(policy_token_account)?;
+                let source = InterfaceAccount::<TokenAccount>::try_from(&accounts[0])?;
+                let holder = resolve_claim_holder(&config, &source, owner_account.key)?;
+
+                process_claim(
+                    &config,
+                    &zone,
+                    &policy,
+                    &disaster_event,
+                    &policy_token_account,
+                    &holder,
+                    amount,
+                )
             }
             _ => {
                 msg!("Instruction not supported");
@@ -434,255 +703,2551 @@ pub mod earthquake_insurance_hook {
         }
     }
 
+    /// Records the disaster event queued by a `ManualDisasterEvent` change once
+    /// its timelock has passed, tied to the feed's latest round at execution.
     pub fn record_disaster_event(
         ctx: Context<RecordDisasterEvent>,
-        region: Region,
-        magnitude: f64,
+        change_id: u64,
+        zone_id: u64,
     ) -> Result<()> {
+        let pending_change = &ctx.accounts.pending_change;
+        pending_change.check_timelock(Clock::get()?.unix_timestamp)?;
+
+        let (magnitude, epicenter) = match pending_change.change {
+            ParameterChange::ManualDisasterEvent { zone_id: change_zone_id, magnitude, epicenter }
+                if change_zone_id == zone_id => (magnitude, epicenter),
+            _ => {
+                msg!("Change {} does not record an event for zone {}", change_id, zone_id);
+                return Err(error!(EarthquakeInsuranceError::InvalidParameterChange));
+            }
+        };
+
+        let zone = &mut ctx.accounts.zone;
         let disaster_event = &mut ctx.accounts.disaster_event;
-        let clock = Clock::get()?;
-        
-     
-        if ctx.accounts.config.authority != ctx.accounts.authority.key() {
-            return Err(error!(EarthquakeInsuranceError::UnauthorizedClaim));
+
+        let switchboard_feed = &ctx.accounts.switchboard_feed;
+        if switchboard_feed.key() != zone.feed {
+            msg!("Feed not matching configured feed for zone {}", zone_id);
+            return Err(error!(EarthquakeInsuranceError::InvalidFeed));
         }
-        
-   
-        if magnitude < 2.0 || magnitude > 9.0 {
-            return Err(error!(EarthquakeInsuranceError::InvalidOracleData));
+
+        // Tie the event to the oracle round that observed the quake
+        let feed_bytes = switchboard_feed.try_borrow_data()?;
+        let (oracle_round, round_timestamp) = match AggregatorAccountData::new_from_bytes(&feed_bytes) {
+            Ok(data) => (
+                data.latest_confirmed_round.round_open_slot,
+                data.latest_confirmed_round.round_open_timestamp,
+            ),
+            Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
+        };
+
+        if oracle_round <= zone.last_event_round {
+            msg!("Oracle round {} already recorded for zone {}", oracle_round, zone_id);
+            return Err(error!(EarthquakeInsuranceError::EventAlreadyRecorded));
         }
+
+        // Governance-entered magnitudes are flagged so they can be told apart from oracle-derived events
+        let sequence = write_disaster_event(
+            zone,
+            disaster_event,
+            magnitude,
+            epicenter,
+            round_timestamp,
+            oracle_round,
+            true,
+        );
         
-        disaster_event.region = region as u8;
-        disaster_event.magnitude = (magnitude * 100.0) as u64;
-        disaster_event.timestamp = clock.unix_timestamp;
-        disaster_event.verified = true;
-        
-        msg!("Disaster event recorded: Region {:?}, Magnitude {}, Time {}", 
-             region, magnitude, clock.unix_timestamp);
+        msg!("Manual override disaster event #{} recorded: Zone {}, Magnitude {}, Time {}, Oracle round {}", 
+             sequence, zone_id, Magnitude(magnitude), round_timestamp, oracle_round);
         
         Ok(())
     }
 
-    pub fn update_insurance_policy(
-        ctx: Context<UpdateInsurancePolicy>,
-        insurance_amount: Option<u64>,
-        premium: Option<u64>,
-        duration_extension_days: Option<u64>
+    pub fn crank_disaster_event(
+        ctx: Context<CrankDisasterEvent>,
+        zone_id: u64,
     ) -> Result<()> {
-        let mut user_account_data = ctx.accounts.user_account.load_mut::<UserAccount>()?;
-        
-   
-        if user_account_data.owner != ctx.accounts.user.key() {
-            return Err(error!(EarthquakeInsuranceError::UserLocationNotRegistered));
+        let config = &ctx.accounts.config;
+        let zone = &mut ctx.accounts.zone;
+        let switchboard_feed = &ctx.accounts.switchboard_feed;
+
+        if switchboard_feed.key() != zone.feed {
+            msg!("Feed not matching configured feed for zone {}", zone_id);
+            return Err(error!(EarthquakeInsuranceError::InvalidFeed));
         }
-        
-      
-        if user_account_data.has_claimed {
-            return Err(error!(EarthquakeInsuranceError::ClaimAlreadyProcessed));
+
+        let clock = Clock::get()?;
+        let feed_bytes = switchboard_feed.try_borrow_data()?;
+        let feed_data = match AggregatorAccountData::new_from_bytes(&feed_bytes) {
+            Ok(data) => data,
+            Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
+        };
+
+        let oracle_round = feed_data.latest_confirmed_round.round_open_slot;
+        let round_timestamp = feed_data.latest_confirmed_round.round_open_timestamp;
+
+        if feed_data.check_staleness(clock.unix_timestamp, config.max_feed_staleness_seconds).is_err() {
+            msg!("Feed round opened at {} is older than {} seconds",
+                 round_timestamp, config.max_feed_staleness_seconds);
+            return Err(error!(EarthquakeInsuranceError::StaleOracleData));
         }
-        
-   
-        if let Some(amount) = insurance_amount {
-            user_account_data.insurance_amount = amount;
-            msg!("Updated insurance amount to: {}", amount);
+
+        if oracle_round <= zone.last_event_round {
+            msg!("Oracle round {} already recorded for zone {}", oracle_round, zone_id);
+            return Err(error!(EarthquakeInsuranceError::EventAlreadyRecorded));
         }
-        
-   
-        if let Some(new_premium) = premium {
-            user_account_data.premium_paid = new_premium;
-            msg!("Updated premium to: {}", new_premium);
+
+        let magnitude_result = match feed_data.get_result() {
+            Ok(result) => result,
+            Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
+        };
+        let magnitude_scaled = magnitude_from_decimal(&magnitude_result)?;
+
+        // A reading below the threshold only re-arms the zone; it is cranked without an event account
+        if magnitude_scaled < zone.threshold_magnitude {
+            if ctx.accounts.disaster_event.is_some() {
+                msg!("Magnitude {} below threshold {}, no event recorded",
+                     Magnitude(magnitude_scaled), Magnitude(zone.threshold_magnitude));
+                return Err(error!(EarthquakeInsuranceError::MagnitudeBelowThreshold));
+            }
+            zone.above_threshold = false;
+            msg!("Magnitude {} below threshold {}, zone {} re-armed",
+                 Magnitude(magnitude_scaled), Magnitude(zone.threshold_magnitude), zone_id);
+            return Ok(());
         }
-        
-      
-        if let Some(days) = duration_extension_days {
-            let extension_seconds = (days as i64) * 24 * 60 * 60;
-            user_account_data.policy_end_time += extension_seconds;
+
+        // Only the reading that crosses the threshold records an event; later rounds of the same quake don't
+        if zone.above_threshold {
+            msg!("Zone {} is still above threshold since its last event", zone_id);
+            return Err(error!(EarthquakeInsuranceError::EventAlreadyRecorded));
+        }
+
+        let disaster_event = match ctx.accounts.disaster_event.as_mut() {
+            Some(disaster_event) => disaster_event,
+            None => {
+                msg!("Disaster event account required to record magnitude {}", Magnitude(magnitude_scaled));
+                return Err(error!(EarthquakeInsuranceError::InvalidDisasterEvent));
+            }
+        };
+
+        // Zones with epicenter feeds report where the quake hit so payouts can be distance-weighted
+        let epicenter = if zone.has_epicenter_feeds() {
+            let latitude = read_epicenter_feed(
+                ctx.accounts.latitude_feed.as_ref(),
+                &zone.latitude_feed,
+                clock.unix_timestamp,
+                config.max_feed_staleness_seconds,
+            )?;
+            let longitude = read_epicenter_feed(
+                ctx.accounts.longitude_feed.as_ref(),
+                &zone.longitude_feed,
+                clock.unix_timestamp,
+                config.max_feed_staleness_seconds,
+            )?;
+            let depth_km = read_epicenter_feed(
+                ctx.accounts.depth_feed.as_ref(),
+                &zone.depth_feed,
+                clock.unix_timestamp,
+                config.max_feed_staleness_seconds,
+            )? / 1_000_000;
+
+            let epicenter = Epicenter {
+                latitude: i32::try_from(latitude).map_err(|_| error!(EarthquakeInsuranceError::InvalidCoordinates))?,
+                longitude: i32::try_from(longitude).map_err(|_| error!(EarthquakeInsuranceError::InvalidCoordinates))?,
+                depth_km: u32::try_from(depth_km).map_err(|_| error!(EarthquakeInsuranceError::InvalidCoordinates))?,
+            };
+            if !geo::is_valid_coordinate(epicenter.latitude, epicenter.longitude) {
+                return Err(error!(EarthquakeInsuranceError::InvalidCoordinates));
+            }
+            Some(epicenter)
+        } else {
+            None
+        };
+
+        let sequence = write_disaster_event(
+            zone,
+            disaster_event,
+            magnitude_scaled,
+            epicenter,
+            round_timestamp,
+            oracle_round,
+            false,
+        );
+
+        msg!("Disaster event #{} cranked: Zone {}, Magnitude {}, Time {}, Oracle round {}",
+             sequence, zone_id, Magnitude(magnitude_scaled), round_timestamp, oracle_round);
+
+        Ok(())
+    }
+
+    /// Raises coverage or extends a policy against a premium top-up priced at
+    /// the zone rate. Increased coverage only applies to quakes after the
+    /// waiting period.
+    pub fn update_insurance_policy(
+        ctx: Context<UpdateInsurancePolicy>,
+        registrant: Pubkey,
+        policy_id: u64,
+        insurance_amount: Option<u64>,
+        duration_extension_days: Option<u64>
+    ) -> Result<()> {
+        let zone = &ctx.accounts.zone;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let (top_up, new_insurance_amount, new_end_time) = {
+            let mut policy_data = ctx.accounts.policy.load_mut()?;
+            
+       
+            check_policy_holder(&policy_data, &ctx.accounts.policy_token_account, &ctx.accounts.user.key())?;
+
+            if policy_data.mint != ctx.accounts.policy_mint.key() {
+                return Err(error!(EarthquakeInsuranceError::PolicyTokenNotHeld));
+            }
             
-            msg!("Extended policy end time by {} days to: {}", 
-                days, user_account_data.policy_end_time);
+          
+            if policy_data.claimed_amount > 0 {
+                return Err(error!(EarthquakeInsuranceError::ClaimAlreadyProcessed));
+            }
+
+            if zone.zone_id != policy_data.zone_id {
+                return Err(error!(EarthquakeInsuranceError::RegionNotSupported));
+            }
+
+            if current_time > policy_data.policy_end_time {
+                return Err(error!(EarthquakeInsuranceError::PolicyExpired));
+            }
+
+            // Terms cannot be changed once a quake may already be claimable
+            if zone.has_event_since(policy_data.policy_start_time) {
+                msg!("Zone {} recorded event #{} at {} during policy coverage",
+                     zone.zone_id, zone.event_count, zone.last_event_timestamp);
+                return Err(error!(EarthquakeInsuranceError::PolicyUpdateBlocked));
+            }
+
+            policy_data.settle_pending_coverage(current_time);
+            let current_amount = policy_data.latest_insurance_amount();
+            let new_amount = insurance_amount.unwrap_or(current_amount);
+            if new_amount < current_amount {
+                msg!("Coverage cannot be reduced from {} to {}", current_amount, new_amount);
+                return Err(error!(EarthquakeInsuranceError::InvalidPolicyUpdate));
+            }
+
+            // A pending increase would be overwritten, moving its effective time
+            if new_amount > current_amount && policy_data.pending_insurance_amount > 0 {
+                msg!("Coverage increase to {} is already pending until {}",
+                    policy_data.pending_insurance_amount, policy_data.pending_effective_time);
+                return Err(error!(EarthquakeInsuranceError::InvalidPolicyUpdate));
+            }
+
+            let extension_seconds = match duration_extension_days {
+                Some(days) => policy_duration_seconds(days)?,
+                None => 0,
+            };
+            let new_end_time = policy_data.policy_end_time
+                .checked_add(extension_seconds)
+                .ok_or(error!(EarthquakeInsuranceError::InvalidPolicyDuration))?;
+            let effective_time = current_time.max(policy_data.policy_start_time) + zone.waiting_period_seconds;
+
+            // The increase is priced from when it takes effect to the current
+            // end, the extension on the new amount
+            let increase_premium = zone.required_premium(
+                new_amount - current_amount,
+                policy_data.policy_end_time - effective_time.min(policy_data.policy_end_time),
+            )?;
+            let extension_premium = zone.required_premium(new_amount, extension_seconds)?;
+            let top_up = increase_premium
+                .checked_add(extension_premium)
+                .ok_or(error!(EarthquakeInsuranceError::InvalidPolicyUpdate))?;
+
+            if new_amount > current_amount {
+                policy_data.pending_insurance_amount = new_amount;
+                policy_data.pending_effective_time = effective_time;
+                msg!("Policy {} of {} coverage rises to {} at {}",
+                    policy_id, registrant, new_amount, effective_time);
+            }
+
+            if extension_seconds > 0 {
+                policy_data.policy_end_time = new_end_time;
+                msg!("Extended policy end time by {} days to: {}", 
+                    extension_seconds / SECONDS_PER_DAY, new_end_time);
+            }
+
+            policy_data.premium_paid += top_up;
+            (top_up, new_amount, new_end_time)
+        };
+
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: ctx.accounts.vault.to_account_info(),
+                    },
+                ),
+                top_up,
+            )?;
+            ctx.accounts.vault.undistributed_premiums += top_up;
         }
-        
+
+        update_policy_metadata(
+            &ctx.accounts.token_program,
+            &ctx.accounts.policy_mint,
+            &ctx.accounts.policy_authority,
+            ctx.bumps.policy_authority,
+            &ctx.accounts.user,
+            &ctx.accounts.system_program,
+            [
+                ("coverage", new_insurance_amount.to_string()),
+                ("expiry", new_end_time.to_string()),
+            ],
+        )?;
+
+        msg!("Premium top-up of {} collected", top_up);
+        Ok(())
+    }
+
+    pub fn create_tranche(
+        ctx: Context<CreateTranche>,
+        kind: TrancheKind,
+        provider: Pubkey,
+        attachment_point: u64,
+        premium_share_bps: u16
+    ) -> Result<()> {
+        if premium_share_bps > BASIS_POINTS_DIVISOR {
+            return Err(error!(EarthquakeInsuranceError::InvalidTrancheConfig));
+        }
+
+        let tranche = &mut ctx.accounts.tranche;
+        tranche.kind = kind;
+        tranche.provider = provider;
+        tranche.attachment_point = attachment_point;
+        tranche.premium_share_bps = premium_share_bps;
+        tranche.capital_deposited = 0;
+        tranche.remaining_capital = 0;
+        tranche.losses_absorbed = 0;
+        tranche.premiums_owed = 0;
+        tranche.bump = ctx.bumps.tranche;
+        check_tranche_layers(tranche, &ctx.accounts.other_tranche)?;
+
+        msg!("{:?} tranche created for {} attaching at {} with {} bps of premiums",
+            kind, provider, attachment_point, premium_share_bps);
+        Ok(())
+    }
+
+    pub fn deposit_tranche_capital(
+        ctx: Context<DepositTrancheCapital>,
+        kind: TrancheKind,
+        amount: u64
+    ) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.provider.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let tranche = &mut ctx.accounts.tranche;
+        tranche.capital_deposited += amount;
+        tranche.remaining_capital += amount;
+        check_tranche_layers(tranche, &ctx.accounts.other_tranche)?;
+
+        msg!("Deposited {} into {:?} tranche, remaining capital {}", amount, kind, tranche.remaining_capital);
+        Ok(())
+    }
+
+    /// Credits each tranche its share of the premiums collected since the
+    /// last distribution; the rest stays with the pool.
+    pub fn distribute_premiums(ctx: Context<DistributePremiums>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let junior = &mut ctx.accounts.junior_tranche;
+        let senior = &mut ctx.accounts.senior_tranche;
+
+        if junior.premium_share_bps as u32 + senior.premium_share_bps as u32 > BASIS_POINTS_DIVISOR as u32 {
+            return Err(error!(EarthquakeInsuranceError::InvalidTrancheConfig));
+        }
+
+        let premiums = vault.undistributed_premiums;
+        let junior_share = junior.premium_share_of(premiums);
+        let senior_share = senior.premium_share_of(premiums);
+
+        junior.premiums_owed += junior_share;
+        senior.premiums_owed += senior_share;
+        vault.undistributed_premiums = 0;
+
+        msg!("Distributed premiums of {}: junior {}, senior {}", premiums, junior_share, senior_share);
+        Ok(())
+    }
+
+    pub fn withdraw_tranche_premiums(ctx: Context<WithdrawTranchePremiums>, kind: TrancheKind) -> Result<()> {
+        let amount = ctx.accounts.tranche.premiums_owed;
+
+        pay_from_vault(&ctx.accounts.vault, &ctx.accounts.provider.to_account_info(), amount)?;
+        ctx.accounts.tranche.premiums_owed = 0;
+
+        msg!("Withdrew {} of {:?} tranche premiums", amount, kind);
         Ok(())
     }
+
+    /// Reports each tranche's capital and attachment point as return data.
+    pub fn get_tranche_status(ctx: Context<GetTrancheStatus>) -> Result<Vec<TrancheStatus>> {
+        let statuses: Vec<TrancheStatus> = [&ctx.accounts.junior_tranche, &ctx.accounts.senior_tranche]
+            .iter()
+            .map(|tranche| TrancheStatus {
+                kind: tranche.kind,
+                attachment_point: tranche.attachment_point,
+                capital_deposited: tranche.capital_deposited,
+                remaining_capital: tranche.remaining_capital,
+                losses_absorbed: tranche.losses_absorbed,
+            })
+            .collect();
+
+        for status in &statuses {
+            msg!("{:?} tranche: attachment {}, remaining capital {} of {}",
+                status.kind, status.attachment_point, status.remaining_capital, status.capital_deposited);
+        }
+        Ok(statuses)
+    }
+}
+
+/// Checks a claim against the user's policy and the disaster event it references,
+/// then records it on the policy as pending settlement.
+fn process_claim<'info>(
+    config: &InsuranceConfig,
+    zone: &CoverageZone,
+    policy: &AccountLoader<'info, Policy>,
+    disaster_event: &Account<'info, DisasterEvent>,
+    policy_token_account: &TokenAccount,
+    owner: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let mut policy_data = policy.load_mut()?;
+
+    if policy_data.pending_claim_amount > 0 {
+        msg!("Policy {} has a claim of {} pending until {}",
+            policy_data.policy_id, policy_data.pending_claim_amount, policy_data.claim_challenge_deadline);
+        return Err(error!(EarthquakeInsuranceError::ClaimPending));
+    }
+
+    // Payouts follow the policy token, not the original registrant
+    check_policy_holder(&policy_data, policy_token_account, owner)?;
+
+    let clock = Clock::get()?;
+    let current_time = clock.unix_timestamp;
+
+    if current_time < policy_data.policy_start_time {
+        msg!("Insurance policy not active yet");
+        return Err(error!(EarthquakeInsuranceError::PolicyNotActive));
+    }
+
+    if zone.zone_id != policy_data.zone_id {
+        msg!("Zone {} does not cover policy zone {}", zone.zone_id, policy_data.zone_id);
+        return Err(error!(EarthquakeInsuranceError::RegionNotSupported));
+    }
+
+    // Quakes during coverage that are reported late stay claimable for the grace period
+    if current_time > policy_data.policy_end_time + zone.grace_period_seconds {
+        msg!("Insurance policy has expired");
+        return Err(error!(EarthquakeInsuranceError::PolicyExpired));
+    }
+
+    if disaster_event.zone_id != policy_data.zone_id {
+        msg!("Disaster event #{} is for zone {}, policy covers zone {}",
+             disaster_event.sequence, disaster_event.zone_id, policy_data.zone_id);
+        return Err(error!(EarthquakeInsuranceError::InvalidDisasterEvent));
+    }
+
+    if disaster_event.key() == policy_data.rejected_claim_event {
+        msg!("Claim against disaster event #{} was rejected by an upheld dispute", disaster_event.sequence);
+        return Err(error!(EarthquakeInsuranceError::ClaimEventRejected));
+    }
+
+    // Only quakes observed during coverage count, not a feed still elevated from before
+    if disaster_event.timestamp < policy_data.policy_start_time
+        || disaster_event.timestamp > policy_data.policy_end_time
+    {
+        msg!("Disaster event #{} at {} is outside coverage [{}, {}]",
+             disaster_event.sequence, disaster_event.timestamp,
+             policy_data.policy_start_time, policy_data.policy_end_time);
+        return Err(error!(EarthquakeInsuranceError::EventOutsideCoverage));
+    }
+
+    msg!("Disaster event #{}, magnitude: {}, oracle round: {}",
+         disaster_event.sequence, Magnitude(disaster_event.magnitude), disaster_event.oracle_round);
+
+    // Weight the quake by how far the insured location is from its hypocenter
+    let magnitude_scaled = if disaster_event.has_epicenter {
+        let surface_distance_km = geo::surface_distance_km(
+            policy_data.latitude,
+            policy_data.longitude,
+            disaster_event.epicenter_latitude,
+            disaster_event.epicenter_longitude,
+        );
+        let distance_km = geo::hypocentral_distance_km(surface_distance_km, disaster_event.depth_km);
+        let felt_magnitude = geo::attenuated_magnitude(disaster_event.magnitude, distance_km);
+
+        msg!("Hypocentral distance: {} km, felt magnitude: {}",
+             distance_km, Magnitude(felt_magnitude));
+        felt_magnitude
+    } else {
+        disaster_event.magnitude
+    };
+    msg!("Threshold magnitude: {}", Magnitude(zone.threshold_magnitude));
+
+    if magnitude_scaled < zone.threshold_magnitude {
+        msg!("Magnitude below threshold, rejecting claim");
+        return Err(error!(EarthquakeInsuranceError::MagnitudeBelowThreshold));
+    }
+
+    let payout_bps = config.payout_bps_for_magnitude(magnitude_scaled);
+    if payout_bps == 0 {
+        msg!("Magnitude below the lowest payout tier, rejecting claim");
+        return Err(error!(EarthquakeInsuranceError::MagnitudeBelowThreshold));
+    }
+
+    let entitled_amount = policy_data.payout_for_bps(payout_bps, disaster_event.timestamp);
+    if policy_data.claimed_amount >= entitled_amount {
+        msg!("Payout for this magnitude already claimed: {}", policy_data.claimed_amount);
+        return Err(error!(EarthquakeInsuranceError::ClaimAlreadyProcessed));
+    }
+
+    // A larger quake later in the policy period tops up earlier claims
+    let remaining_amount = entitled_amount - policy_data.claimed_amount;
+    if amount > remaining_amount {
+        msg!("Claim amount exceeds payout for this magnitude: {} > {}", amount, remaining_amount);
+        return Err(error!(EarthquakeInsuranceError::ExcessClaimAmount));
+    }
+
+    // Paid out by finalize_claim once the challenge window passes undisputed
+    policy_data.pending_claim_amount = amount;
+    policy_data.pending_claim_claimant = *owner;
+    policy_data.pending_claim_event = disaster_event.key();
+    policy_data.claim_challenge_deadline = current_time + config.challenge_window_seconds;
+
+    msg!("Payout tier {} bps of {}, claim for amount {} open to challenge until {}",
+        payout_bps, policy_data.insurance_amount, amount, policy_data.claim_challenge_deadline);
+    Ok(())
+}
+
+/// Runs a claim loss through the waterfall: the pool retains losses below the
+/// junior attachment point, then the junior and senior tranches absorb their
+/// layers in order.
+fn absorb_claim_loss(
+    vault: &mut InsuranceVault,
+    junior_tranche: Option<&mut Tranche>,
+    senior_tranche: Option<&mut Tranche>,
+    amount: u64,
+) -> Result<()> {
+    let cumulative_losses = vault.total_losses;
+    let junior_loss = junior_tranche.map_or(0, |tranche| tranche.absorb_loss(cumulative_losses, amount));
+    let senior_loss = senior_tranche.map_or(0, |tranche| tranche.absorb_loss(cumulative_losses, amount));
+    vault.total_losses = cumulative_losses.saturating_add(amount);
+
+    msg!("Claim loss {}: junior {}, senior {}, retained {}",
+        amount, junior_loss, senior_loss, amount.saturating_sub(junior_loss).saturating_sub(senior_loss));
+    Ok(())
+}
+
+/// Takes a cancellation refund back out of premium income: first from
+/// premiums not yet distributed, then from each tranche's share of the part
+/// already distributed, with the pool covering its own retained share. Fails
+/// if a tranche has already withdrawn the premiums it would give back.
+fn return_refunded_premiums(
+    vault: &mut InsuranceVault,
+    junior_tranche: Option<&mut Tranche>,
+    senior_tranche: Option<&mut Tranche>,
+    refund: u64,
+) -> Result<()> {
+    let undistributed = refund.min(vault.undistributed_premiums);
+    vault.undistributed_premiums -= undistributed;
+
+    let distributed = refund - undistributed;
+    for tranche in [junior_tranche, senior_tranche].into_iter().flatten() {
+        let share = tranche.premium_share_of(distributed);
+        tranche.premiums_owed = tranche.premiums_owed.checked_sub(share).ok_or_else(|| {
+            msg!("{:?} tranche owes {} of the refund but only {} is unwithdrawn",
+                tranche.kind, share, tranche.premiums_owed);
+            error!(EarthquakeInsuranceError::PremiumsAlreadyWithdrawn)
+        })?;
+    }
+    Ok(())
+}
+
+/// Reads a tranche PDA, or `None` if that tranche hasn't been created.
+fn load_tranche(tranche: &AccountInfo) -> Result<Option<Tranche>> {
+    if tranche.owner != &crate::ID || tranche.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Tranche::try_deserialize(&mut &tranche.try_borrow_data()?[..])?))
+}
+
+fn store_tranche(info: &AccountInfo, tranche: &Tranche) -> Result<()> {
+    tranche.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+/// Checks that the senior layer attaches at or above the top of the junior
+/// layer. Passes while the other tranche hasn't been created.
+fn check_tranche_layers(tranche: &Tranche, other_tranche: &AccountInfo) -> Result<()> {
+    let other = match load_tranche(other_tranche)? {
+        Some(other) => other,
+        None => return Ok(()),
+    };
+    let (junior, senior) = match tranche.kind {
+        TrancheKind::Junior => (tranche, &other),
+        TrancheKind::Senior => (&other, tranche),
+    };
+
+    let junior_top = junior
+        .attachment_point
+        .checked_add(junior.capital_deposited)
+        .ok_or(error!(EarthquakeInsuranceError::InvalidTrancheConfig))?;
+    if senior.attachment_point < junior_top {
+        msg!("Senior tranche attaching at {} overlaps the junior layer ending at {}",
+            senior.attachment_point, junior_top);
+        return Err(error!(EarthquakeInsuranceError::InvalidTrancheConfig));
+    }
+    Ok(())
+}
+
+/// Moves lamports out of the vault, keeping it rent exempt.
+fn pay_from_vault<'info>(
+    vault: &Account<'info, InsuranceVault>,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let vault_info = vault.to_account_info();
+    let rent_floor = Rent::get()?.minimum_balance(vault_info.data_len());
+    if vault_info.lamports().saturating_sub(amount) < rent_floor {
+        msg!("Vault cannot cover payment of {}", amount);
+        return Err(error!(EarthquakeInsuranceError::InsufficientVaultFunds));
+    }
+
+    **vault_info.try_borrow_mut_lamports()? -= amount;
+    **recipient.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Converts a policy duration or extension in days to seconds, rejecting
+/// zero and lengths that do not fit a timestamp.
+fn policy_duration_seconds(days: u64) -> Result<i64> {
+    i64::try_from(days)
+        .ok()
+        .filter(|days| *days > 0)
+        .and_then(|days| days.checked_mul(SECONDS_PER_DAY))
+        .ok_or(error!(EarthquakeInsuranceError::InvalidPolicyDuration))
+}
+
+/// Requires `holder` to own the policy token through `policy_token_account`.
+fn check_policy_holder(policy: &Policy, policy_token_account: &TokenAccount, holder: &Pubkey) -> Result<()> {
+    if policy_token_account.mint != policy.mint
+        || policy_token_account.owner != *holder
+        || policy_token_account.amount == 0
+    {
+        msg!("{} does not hold policy {} of {}", holder, policy.policy_id, policy.registrant);
+        return Err(error!(EarthquakeInsuranceError::PolicyTokenNotHeld));
+    }
+    Ok(())
+}
+
+/// Burns the whole balance of a policy token account using the program's
+/// permanent delegate authority.
+fn burn_policy_token_from<'info>(
+    token_program: &Program<'info, Token2022>,
+    policy_mint: &InterfaceAccount<'info, Mint>,
+    policy_token_account: &InterfaceAccount<'info, TokenAccount>,
+    policy_authority: &UncheckedAccount<'info>,
+    policy_authority_bump: u8,
+) -> Result<()> {
+    let authority_seeds: &[&[u8]] = &[b"policy_authority", &[policy_authority_bump]];
+    token_interface::burn(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Burn {
+                mint: policy_mint.to_account_info(),
+                from: policy_token_account.to_account_info(),
+                authority: policy_authority.to_account_info(),
+            },
+            &[authority_seeds],
+        ),
+        policy_token_account.amount,
+    )
+}
+
+/// Claims are only recorded from inside a real Token-2022 transfer: both token
+/// accounts must belong to the mint, and Token-2022 flags the source as
+/// transferring for the duration of the hook CPI.
+fn check_is_transferring<'a>(source: &AccountInfo<'a>, mint: &AccountInfo, destination: &AccountInfo<'a>) -> Result<()> {
+    for account in [source, destination] {
+        if account.owner != &anchor_spl::token_2022::ID {
+            return Err(error!(EarthquakeInsuranceError::NotTransferring));
+        }
+        let data = account.try_borrow_data()?;
+        let token_account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+        if token_account.base.mint != *mint.key {
+            return Err(error!(EarthquakeInsuranceError::NotTransferring));
+        }
+    }
+
+    let data = source.try_borrow_data()?;
+    let source_account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    let hook_account = source_account.get_extension::<TransferHookAccount>()?;
+    if !bool::from(hook_account.transferring) {
+        msg!("Transfer hook called outside of a token transfer");
+        return Err(error!(EarthquakeInsuranceError::NotTransferring));
+    }
+    Ok(())
+}
+
+/// Claims are attributed to the owner of the source token account. Token-2022
+/// passes the transfer authority as `owner`, which is an SPL delegate or the
+/// permanent delegate when the owner didn't sign themselves.
+fn resolve_claim_holder(config: &InsuranceConfig, source: &TokenAccount, authority: &Pubkey) -> Result<Pubkey> {
+    if source.owner != *authority && !config.allow_delegate_transfers {
+        msg!("Transfer signed by {} on behalf of {}", authority, source.owner);
+        return Err(error!(EarthquakeInsuranceError::DelegateTransferNotAllowed));
+    }
+    Ok(source.owner)
+}
+
+/// Accounts the transfer hook needs beyond those passed by Token-2022.
+fn claim_extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    // Accounts 0-4 are source, mint, destination, owner and this list; the
+    // user account, the policy it selects for claims, the policy's zone, the
+    // zone's latest event and the holder's policy token account derive from them.
+    // The holder is the source account's owner, read from its data, since the
+    // owner account is the delegate for delegated transfers.
+    // The zone's feed is not needed: events are recorded from it beforehand,
+    // so a plain transfer_checked resolves every account on its own
+    Ok(vec![
+        ExtraAccountMeta::new_with_seeds(
+            &[Seed::Literal { bytes: b"config".to_vec() }],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"user".to_vec() },
+                Seed::AccountData { account_index: 0, data_index: TOKEN_ACCOUNT_OWNER_OFFSET, length: 32 },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"policy".to_vec() },
+                Seed::AccountData { account_index: 6, data_index: USER_CLAIM_REGISTRANT_OFFSET, length: 32 },
+                Seed::AccountData { account_index: 6, data_index: USER_CLAIM_POLICY_ID_OFFSET, length: 8 },
+            ],
+            false,
+            true,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"zone".to_vec() },
+                Seed::AccountData { account_index: 7, data_index: POLICY_ZONE_ID_OFFSET, length: 8 },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"disaster".to_vec() },
+                Seed::AccountData { account_index: 7, data_index: POLICY_ZONE_ID_OFFSET, length: 8 },
+                Seed::AccountData { account_index: 8, data_index: ZONE_EVENT_COUNT_OFFSET, length: 8 },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_pubkey(&anchor_spl::associated_token::ID, false, false)?,
+        // Associated token account of the holder for the policy mint
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            10,
+            &[
+                Seed::AccountData { account_index: 0, data_index: TOKEN_ACCOUNT_OWNER_OFFSET, length: 32 },
+                Seed::Literal { bytes: anchor_spl::token_2022::ID.to_bytes().to_vec() },
+                Seed::AccountData { account_index: 7, data_index: POLICY_MINT_OFFSET, length: 32 },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
+
+/// Creates the list PDA with `size` bytes. `create_account` fails once anyone
+/// has sent lamports to the address, so a funded PDA is topped up to rent
+/// exemption and then allocated and assigned to the program instead.
+fn create_extra_account_meta_list<'info>(
+    list: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+    signer_seeds: &[&[u8]],
+    size: usize,
+) -> Result<()> {
+    if list.lamports() == 0 {
+        let lamports = Rent::get()?.minimum_balance(size);
+        msg!("Creating account with {} lamports for rent exemption", lamports);
+        invoke_signed(
+            &system_instruction::create_account(payer.key, list.key, lamports, size as u64, program_id),
+            &[payer.to_account_info(), list.clone(), system_program.to_account_info()],
+            &[signer_seeds],
+        )?;
+        return Ok(());
+    }
+
+    msg!("List {} already holds {} lamports, allocating it in place", list.key, list.lamports());
+    fund_rent(payer, list, system_program, size)?;
+    invoke_signed(
+        &system_instruction::allocate(list.key, size as u64),
+        &[list.clone(), system_program.to_account_info()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(list.key, program_id),
+        &[list.clone(), system_program.to_account_info()],
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+/// Resizes an existing list to `size` bytes, topping up rent from `payer`
+/// when it grows and returning the excess when it shrinks.
+fn resize_extra_account_meta_list<'info>(
+    list: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    size: usize,
+) -> Result<()> {
+    let current_size = list.data_len();
+    if current_size == size {
+        return Ok(());
+    }
+
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(size);
+    let lamports = list.lamports();
+    if rent_exempt_lamports > lamports {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: list.clone(),
+                },
+            ),
+            rent_exempt_lamports - lamports,
+        )?;
+    } else if lamports > rent_exempt_lamports {
+        **list.try_borrow_mut_lamports()? -= lamports - rent_exempt_lamports;
+        **payer.try_borrow_mut_lamports()? += lamports - rent_exempt_lamports;
+    }
+
+    list.realloc(size, false)?;
+    msg!("Extra account meta list resized from {} to {} bytes", current_size, size);
+    Ok(())
+}
+
+/// Only the mint's transfer-hook authority may manage its extra account meta
+/// list, and only for mints whose hook points at this program.
+fn check_transfer_hook_authority(program_id: &Pubkey, mint: &AccountInfo, authority: &Pubkey) -> Result<()> {
+    if mint.owner != &anchor_spl::token_2022::ID {
+        return Err(error!(EarthquakeInsuranceError::InvalidHookProgram));
+    }
+
+    let hook = token_interface::get_mint_extension_data::<TransferHookExtension>(mint)?;
+    if Option::<Pubkey>::from(hook.program_id) != Some(*program_id) {
+        msg!("Mint {} does not use this program as its transfer hook", mint.key);
+        return Err(error!(EarthquakeInsuranceError::InvalidHookProgram));
+    }
+    if Option::<Pubkey>::from(hook.authority) != Some(*authority) {
+        msg!("Signer {} is not the transfer hook authority of mint {}", authority, mint.key);
+        return Err(error!(EarthquakeInsuranceError::UnauthorizedHookAuthority));
+    }
+    Ok(())
+}
+
+/// Tops up `account` so it stays rent exempt at `new_size` bytes.
+fn fund_rent<'info>(
+    payer: &Signer<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    new_size: usize,
+) -> Result<()> {
+    let extra_rent = Rent::get()?.minimum_balance(new_size).saturating_sub(account.lamports());
+    if extra_rent > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            extra_rent,
+        )?;
+    }
+    Ok(())
+}
+
+/// Rewrites additional metadata fields on a policy token.
+fn update_policy_metadata<'info, const N: usize>(
+    token_program: &Program<'info, Token2022>,
+    policy_mint: &InterfaceAccount<'info, Mint>,
+    policy_authority: &UncheckedAccount<'info>,
+    policy_authority_bump: u8,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    fields: [(&str, String); N],
+) -> Result<()> {
+    let mint = policy_mint.to_account_info();
+
+    let new_size = {
+        let data = mint.try_borrow_data()?;
+        let state = StateWithExtensions::<MintState>::unpack(&data)?;
+        let mut metadata = state.get_variable_len_extension::<TokenMetadata>()?;
+        let old_metadata_size = metadata.tlv_size_of()?;
+        for (key, value) in &fields {
+            metadata.update(Field::Key(key.to_string()), value.clone());
+        }
+        data.len() - old_metadata_size + metadata.tlv_size_of()?
+    };
+    fund_rent(payer, &mint, system_program, new_size)?;
+
+    let authority_seeds: &[&[u8]] = &[b"policy_authority", &[policy_authority_bump]];
+    for (key, value) in fields {
+        token_interface::token_metadata_update_field(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TokenMetadataUpdateField {
+                    token_program_id: token_program.to_account_info(),
+                    metadata: mint.clone(),
+                    update_authority: policy_authority.to_account_info(),
+                },
+                &[authority_seeds],
+            ),
+            Field::Key(key.to_string()),
+            value,
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the policy token metadata, mints the single token to the registrant
+/// and removes the mint authority so the supply stays at one.
+fn mint_policy_token<'info>(
+    accounts: &RegisterUserLocation<'info>,
+    policy_authority_bump: u8,
+    policy_id: u64,
+    zone_id: u64,
+    insurance_amount: u64,
+    end_timestamp: i64,
+) -> Result<()> {
+    let authority_seeds: &[&[u8]] = &[b"policy_authority", &[policy_authority_bump]];
+    let signer_seeds = &[authority_seeds];
+    let token_program = accounts.token_program.to_account_info();
+    let mint = accounts.policy_mint.to_account_info();
+    let policy_authority = accounts.policy_authority.to_account_info();
+
+    let name = format!("Earthquake Policy #{}", policy_id);
+    let symbol = String::from("EQPOL");
+    let uri = String::new();
+    let fields = [
+        ("zone", zone_id.to_string()),
+        ("coverage", insurance_amount.to_string()),
+        ("expiry", end_timestamp.to_string()),
+    ];
+
+    // Token-2022 grows the mint for the metadata but the extra rent must be there first
+    let metadata = TokenMetadata {
+        update_authority: OptionalNonZeroPubkey::try_from(Some(policy_authority.key()))?,
+        mint: mint.key(),
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        additional_metadata: fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+    };
+    let new_size = mint.data_len() + metadata.tlv_size_of()?;
+    fund_rent(&accounts.user, &mint, &accounts.system_program, new_size)?;
+
+    token_interface::token_metadata_initialize(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TokenMetadataInitialize {
+                token_program_id: token_program.clone(),
+                metadata: mint.clone(),
+                update_authority: policy_authority.clone(),
+                mint_authority: policy_authority.clone(),
+                mint: mint.clone(),
+            },
+            signer_seeds,
+        ),
+        name,
+        symbol,
+        uri,
+    )?;
+
+    for (key, value) in fields {
+        token_interface::token_metadata_update_field(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                TokenMetadataUpdateField {
+                    token_program_id: token_program.clone(),
+                    metadata: mint.clone(),
+                    update_authority: policy_authority.clone(),
+                },
+                signer_seeds,
+            ),
+            Field::Key(key.to_string()),
+            value,
+        )?;
+    }
+
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            MintTo {
+                mint: mint.clone(),
+                to: accounts.policy_token_account.to_account_info(),
+                authority: policy_authority.clone(),
+            },
+            signer_seeds,
+        ),
+        1,
+    )?;
+
+    token_interface::set_authority(
+        CpiContext::new_with_signer(
+            token_program,
+            SetAuthority {
+                current_authority: policy_authority,
+                account_or_mint: mint,
+            },
+            signer_seeds,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Stores a disaster event under the zone's next sequence number and returns it.
+fn write_disaster_event(
+    zone: &mut CoverageZone,
+    disaster_event: &mut DisasterEvent,
+    magnitude: u64,
+    epicenter: Option<Epicenter>,
+    timestamp: i64,
+    oracle_round: u64,
+    manual_override: bool,
+) -> u64 {
+    let sequence = zone.next_event_sequence();
+    zone.event_count = sequence;
+    zone.last_event_round = oracle_round;
+    zone.last_event_timestamp = timestamp;
+    zone.above_threshold = true;
+
+    disaster_event.zone_id = zone.zone_id;
+    disaster_event.sequence = sequence;
+    disaster_event.magnitude = magnitude;
+    if let Some(epicenter) = epicenter {
+        disaster_event.epicenter_latitude = epicenter.latitude;
+        disaster_event.epicenter_longitude = epicenter.longitude;
+        disaster_event.depth_km = epicenter.depth_km;
+        disaster_event.has_epicenter = true;
+    }
+    disaster_event.timestamp = timestamp;
+    disaster_event.oracle_round = oracle_round;
+    disaster_event.verified = true;
+    disaster_event.manual_override = manual_override;
+
+    sequence
+}
+
+/// Converts a Switchboard feed result to a magnitude scaled by 100, rounding
+/// down so a reading never clears a threshold it didn't reach.
+fn magnitude_from_decimal(value: &SwitchboardDecimal) -> Result<u64> {
+    rescale_magnitude(value).ok_or(error!(EarthquakeInsuranceError::InvalidOracleData))
+}
+
+/// Rescales `mantissa * 10^-scale` to hundredths with checked integer math,
+/// rounding down, or `None` on overflow or a negative value.
+fn rescale_magnitude(value: &SwitchboardDecimal) -> Option<u64> {
+    if value.mantissa < 0 {
+        return None;
+    }
+
+    let scaled = if value.scale >= MAGNITUDE_DECIMALS {
+        let divisor = 10i128.checked_pow(value.scale - MAGNITUDE_DECIMALS)?;
+        value.mantissa / divisor
+    } else {
+        let multiplier = 10i128.checked_pow(MAGNITUDE_DECIMALS - value.scale)?;
+        value.mantissa.checked_mul(multiplier)?
+    };
+
+    u64::try_from(scaled).ok()
+}
+
+/// Reads a fresh epicenter coordinate or depth from its feed, in millionths.
+fn read_epicenter_feed(
+    feed: Option<&UncheckedAccount>,
+    expected_feed: &Pubkey,
+    unix_timestamp: i64,
+    max_staleness_seconds: i64,
+) -> Result<i64> {
+    let feed = match feed {
+        Some(feed) if feed.key() == *expected_feed => feed,
+        _ => {
+            msg!("Epicenter feed {} not provided", expected_feed);
+            return Err(error!(EarthquakeInsuranceError::InvalidFeed));
+        }
+    };
+
+    let feed_bytes = feed.try_borrow_data()?;
+    let feed_data = match AggregatorAccountData::new_from_bytes(&feed_bytes) {
+        Ok(data) => data,
+        Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
+    };
+
+    if feed_data.check_staleness(unix_timestamp, max_staleness_seconds).is_err() {
+        msg!("Epicenter feed {} is stale", expected_feed);
+        return Err(error!(EarthquakeInsuranceError::StaleOracleData));
+    }
+
+    let value = match feed_data.get_result() {
+        Ok(result) => result,
+        Err(_) => return Err(error!(EarthquakeInsuranceError::InvalidOracleData)),
+    };
+
+    let millionths = if value.scale >= 6 {
+        10i128.checked_pow(value.scale - 6).and_then(|divisor| value.mantissa.checked_div(divisor))
+    } else {
+        10i128.checked_pow(6 - value.scale).and_then(|factor| value.mantissa.checked_mul(factor))
+    };
+
+    millionths
+        .and_then(|v| i64::try_from(v).ok())
+        .ok_or(error!(EarthquakeInsuranceError::InvalidOracleData))
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 1 + MAX_PAYOUT_TIERS * (8 + 2) + 8 + 2 + 8 + 8 + 32 + 32 + 8 + 8 + 1, // discriminator + authority + payout schedule + max staleness + cancellation fee + dispute settings + timelock + delegate flag
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 1 + 8 + 8,
+        seeds = [b"vault"],
+        bump
+    )]
+    pub vault: Account<'info, InsuranceVault>,
+    
+    /// Only the upgrade authority can claim the config, so deployment can't be front-run
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::EarthquakeInsuranceHook>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ EarthquakeInsuranceError::UnauthorizedInitializer
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump,
+        constraint = config.authority == authority.key() @ EarthquakeInsuranceError::UnauthorizedClaim
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 8 + 32 + 8 + PARAMETER_CHANGE_MAX_SIZE + 1,
+        seeds = [b"change", config.change_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64)]
+pub struct ExecuteChange<'info> {
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"change", change_id.to_le_bytes().as_ref()],
+        bump = pending_change.bump,
+        has_one = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// Coverage zone targeted by zone changes
+    #[account(mut)]
+    pub zone: Option<Account<'info, CoverageZone>>,
+
+    /// CHECK: Proposer recorded on the change, receives its rent
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64)]
+pub struct CancelChange<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.guardian == guardian.key() @ EarthquakeInsuranceError::UnauthorizedClaim
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"change", change_id.to_le_bytes().as_ref()],
+        bump = pending_change.bump,
+        has_one = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// CHECK: Proposer recorded on the change, receives its rent
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64, zone_id: u64)]
+pub struct CreateCoverageZone<'info> {
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + 8 + 8 + 32 + 8 + 2 + 8 + 8 + 1 + 1 + 32 * 3 + 8 + 8 + 1,
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"change", change_id.to_le_bytes().as_ref()],
+        bump = pending_change.bump,
+        has_one = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// CHECK: Proposer recorded on the change, receives its rent
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zone_id: u64)]
+pub struct RetireCoverageZone<'info> {
+    #[account(
+        mut,
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.authority == authority.key() @ EarthquakeInsuranceError::UnauthorizedClaim
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(zone_id: u64)]
+pub struct RegisterUserLocation<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 8 + 8 + 32,
+        seeds = [b"user", user.key().as_ref()],
+        bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 32 + 32 + 8 + 4 + 4 + 32,
+        seeds = [b"policy", user.key().as_ref(), user_account.policy_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"policy_mint", policy.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = policy_authority,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = policy_authority,
+        extensions::metadata_pointer::metadata_address = policy_mint,
+        extensions::permanent_delegate::delegate = policy_authority,
+    )]
+    pub policy_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = policy_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA that holds mint, metadata and permanent delegate authority over policy tokens
+    #[account(seeds = [b"policy_authority"], bump)]
+    pub policy_authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct SelectClaimPolicy<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 8 + 8 + 32,
+        seeds = [b"user", user.key().as_ref()],
+        bump
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    /// Caller's token account for the policy mint
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct BurnPolicyToken<'info> {
+    #[account(
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(mut)]
+    pub policy_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = policy_mint,
+        token::token_program = token_program,
+    )]
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Permanent delegate PDA of the policy mint
+    #[account(seeds = [b"policy_authority"], bump)]
+    pub policy_authority: UncheckedAccount<'info>,
+
+    /// Coverage zone of the policy
+    pub zone: Account<'info, CoverageZone>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct CancelPolicy<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(mut)]
+    pub policy_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = policy_mint,
+        token::token_program = token_program,
+    )]
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Permanent delegate PDA of the policy mint
+    #[account(seeds = [b"policy_authority"], bump)]
+    pub policy_authority: UncheckedAccount<'info>,
+
+    /// Coverage zone of the policy
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
+
+    /// CHECK: Junior tranche PDA; gives back its share of distributed premiums once created
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Junior as u8]], bump)]
+    pub junior_tranche: UncheckedAccount<'info>,
+
+    /// CHECK: Senior tranche PDA; gives back its share of distributed premiums once created
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Senior as u8]], bump)]
+    pub senior_tranche: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    /// CHECK: Validation account the token program reads extra accounts from
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_metas: AccountInfo<'info>,
+    
+    /// CHECK: Token mint the extra account meta list is created for, checked
+    /// against its transfer hook extension
+    pub mint: AccountInfo<'info>,
+    
+    /// Transfer hook authority of the mint
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    /// CHECK: Existing validation account of the mint
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub extra_account_metas: AccountInfo<'info>,
+    
+    /// CHECK: Token mint the list belongs to, checked against its transfer hook extension
+    pub mint: AccountInfo<'info>,
+    
+    /// Transfer hook authority of the mint, pays for extra space and gets
+    /// back rent freed by a smaller list
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    /// Checked to be a transferring Token-2022 account of the mint; its owner
+    /// is the policy holder
+    pub source: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: Passed from token program via CPI
+    pub mint: AccountInfo<'info>,
+    
+    /// CHECK: Checked to be a Token-2022 account of the mint
+    pub destination: AccountInfo<'info>,
+    
+    /// CHECK: Transfer authority, the source owner or one of its delegates
+    pub owner: AccountInfo<'info>,
+    
+    /// CHECK: Extra account meta list
+    pub extra_account_meta_list: AccountInfo<'info>,
+    
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, InsuranceConfig>,
+    
+    #[account(seeds = [b"user", source.owner.as_ref()], bump)]
+    pub user_account: Account<'info, UserAccount>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"policy",
+            user_account.claim_policy_registrant.as_ref(),
+            user_account.claim_policy_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+    
+    /// Coverage zone of the user's policy
+    pub zone: Account<'info, CoverageZone>,
+    
+    /// Disaster event the claim is made against
+    pub disaster_event: Account<'info, DisasterEvent>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    /// Owner's token account for the policy mint
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct SubmitClaim<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    /// Coverage zone of the policy
+    pub zone: Account<'info, CoverageZone>,
+
+    /// Disaster event the claim is made against
+    pub disaster_event: Account<'info, DisasterEvent>,
+
+    /// Claimant's token account for the policy mint
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub claimant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct DisputeClaim<'info> {
+    #[account(
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(
+        init,
+        payer = challenger,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 32 + 1,
+        seeds = [b"dispute", policy.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, ClaimDispute>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, InsuranceConfig>,
+
+    #[account(mut)]
+    pub challenger: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct ResolveDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(
+        mut,
+        close = challenger,
+        seeds = [b"dispute", policy.key().as_ref()],
+        bump = dispute.bump,
+        constraint = dispute.challenger == challenger.key() @ EarthquakeInsuranceError::UnauthorizedClaim
+    )]
+    pub dispute: Account<'info, ClaimDispute>,
+
+    /// CHECK: Challenger recorded on the dispute, receives the account's lamports
+    #[account(mut)]
+    pub challenger: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.arbiter == arbiter.key() @ EarthquakeInsuranceError::UnauthorizedClaim
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    pub arbiter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct FinalizeClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub policy: AccountLoader<'info, Policy>,
+
+    /// CHECK: Dispute PDA of the policy, must not exist
+    #[account(seeds = [b"dispute", policy.key().as_ref()], bump)]
+    pub dispute: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the claimant recorded on the policy
+    #[account(mut)]
+    pub claimant: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
+
+    /// CHECK: Junior tranche PDA; absorbs its layer of the loss once created
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Junior as u8]], bump)]
+    pub junior_tranche: UncheckedAccount<'info>,
+
+    /// CHECK: Senior tranche PDA; absorbs its layer of the loss once created
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Senior as u8]], bump)]
+    pub senior_tranche: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(change_id: u64, zone_id: u64)]
+pub struct RecordDisasterEvent<'info> {
+    #[account(
+        mut,
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 4 + 4 + 4 + 1,
+        seeds = [
+            b"disaster",
+            zone_id.to_le_bytes().as_ref(),
+            zone.next_event_sequence().to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub disaster_event: Account<'info, DisasterEvent>,
+
+    /// CHECK: Switchboard feed for the zone, checked against `zone.feed`
+    pub switchboard_feed: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"change", change_id.to_le_bytes().as_ref()],
+        bump = pending_change.bump,
+        has_one = proposer
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// CHECK: Proposer recorded on the change, receives its rent
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zone_id: u64)]
+pub struct CrankDisasterEvent<'info> {
+    #[account(
+        mut,
+        seeds = [b"zone", zone_id.to_le_bytes().as_ref()],
+        bump = zone.bump
+    )]
+    pub zone: Account<'info, CoverageZone>,
+
+    /// Left out when cranking a below-threshold reading that re-arms the zone
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 4 + 4 + 4 + 1,
+        seeds = [
+            b"disaster",
+            zone_id.to_le_bytes().as_ref(),
+            zone.next_event_sequence().to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub disaster_event: Option<Account<'info, DisasterEvent>>,
+
+    /// CHECK: Switchboard feed for the zone, checked against `zone.feed`
+    pub switchboard_feed: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Account<'info, InsuranceConfig>,
+
+    /// CHECK: Epicenter latitude feed, checked against `zone.latitude_feed`
+    pub latitude_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Epicenter longitude feed, checked against `zone.longitude_feed`
+    pub longitude_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Hypocenter depth feed in km, checked against `zone.depth_feed`
+    pub depth_feed: Option<UncheckedAccount<'info>>,
+
+    /// Anyone can crank; they only pay rent for the event account
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeConfig<'info> {
+#[instruction(registrant: Pubkey, policy_id: u64)]
+pub struct UpdateInsurancePolicy<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 8 + 32, // discriminator + threshold_magnitude + authority
-        seeds = [b"config"],
+        mut,
+        seeds = [b"policy", registrant.as_ref(), policy_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub config: Account<'info, InsuranceConfig>,
+    pub policy: AccountLoader<'info, Policy>,
+
+    #[account(mut)]
+    pub policy_mint: InterfaceAccount<'info, Mint>,
+
+    /// Caller's token account for the policy mint
+    pub policy_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Metadata update authority PDA of the policy mint
+    #[account(seeds = [b"policy_authority"], bump)]
+    pub policy_authority: UncheckedAccount<'info>,
+
+    /// Coverage zone of the policy
+    pub zone: Account<'info, CoverageZone>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
     
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
     
+    pub token_program: Program<'info, Token2022>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateRegionFeed<'info> {
+#[instruction(kind: TrancheKind)]
+pub struct CreateTranche<'info> {
     #[account(
-        init_if_needed,
+        init,
         payer = authority,
-        space = 8 + 32 * 5, 
-        seeds = [b"region_feeds"],
+        space = 8 + 1 + 32 + 8 + 2 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"tranche".as_ref(), &[kind as u8]],
         bump
     )]
-    pub region_feeds: Account<'info, RegionFeeds>,
-    
+    pub tranche: Account<'info, Tranche>,
+
+    /// CHECK: The other tranche, which may not exist yet; its layer is checked
+    /// against the new one
+    #[account(seeds = [b"tranche".as_ref(), &[kind.other() as u8]], bump)]
+    pub other_tranche: UncheckedAccount<'info>,
+
     #[account(
-        mut,
         seeds = [b"config"],
         bump,
         constraint = config.authority == authority.key() @ EarthquakeInsuranceError::UnauthorizedClaim
     )]
     pub config: Account<'info, InsuranceConfig>,
-    
+
     #[account(mut)]
     pub authority: Signer<'info>,
-    
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RegisterUserLocation<'info> {
+#[instruction(kind: TrancheKind)]
+pub struct DepositTrancheCapital<'info> {
     #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 1 + 1 + 8 + 8 + 8 + 8, 
-        seeds = [b"user", user.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"tranche".as_ref(), &[kind as u8]],
+        bump = tranche.bump,
+        constraint = tranche.provider == provider.key() @ EarthquakeInsuranceError::UnauthorizedClaim
     )]
-    pub user_account: AccountLoader<'info, UserAccount>,
-    
+    pub tranche: Account<'info, Tranche>,
+
+    /// CHECK: The other tranche, which may not exist yet; the grown layer is
+    /// checked against it
+    #[account(seeds = [b"tranche".as_ref(), &[kind.other() as u8]], bump)]
+    pub other_tranche: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
+
     #[account(mut)]
-    pub user: Signer<'info>,
-    
+    pub provider: Signer<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeExtraAccountMetaList<'info> {
+pub struct DistributePremiums<'info> {
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
 
-    pub extra_account_metas: AccountInfo<'info>,
-    
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Junior as u8]], bump = junior_tranche.bump)]
+    pub junior_tranche: Account<'info, Tranche>,
 
-    pub mint: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+    #[account(mut, seeds = [b"tranche", &[TrancheKind::Senior as u8]], bump = senior_tranche.bump)]
+    pub senior_tranche: Account<'info, Tranche>,
 }
 
 #[derive(Accounts)]
-pub struct TransferHook<'info> {
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub source: AccountInfo<'info>,
-    
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub mint: AccountInfo<'info>,
-    
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub destination: AccountInfo<'info>,
-    
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub owner: AccountInfo<'info>,
-    
-    /// CHECK: Extra account meta list
-    pub extra_account_meta_list: AccountInfo<'info>,
-    
-    #[account(seeds = [b"config"], bump)]
-    pub config: Account<'info, InsuranceConfig>,
-    
-    #[account(seeds = [b"region_feeds"], bump)]
-    pub region_feeds: Account<'info, RegionFeeds>,
-    
+#[instruction(kind: TrancheKind)]
+pub struct WithdrawTranchePremiums<'info> {
     #[account(
         mut,
-        seeds = [b"user", owner.key().as_ref()],
-        bump
+        seeds = [b"tranche".as_ref(), &[kind as u8]],
+        bump = tranche.bump,
+        constraint = tranche.provider == provider.key() @ EarthquakeInsuranceError::UnauthorizedClaim
     )]
-    pub user_account: AccountLoader<'info, UserAccount>,
-    
-    /// CHECK: Switchboard feed account provided by transfer hook
-    pub switchboard_feed: AccountInfo<'info>,
-}
+    pub tranche: Account<'info, Tranche>,
+
+    #[account(mut, seeds = [b"vault"], bump = vault.bump)]
+    pub vault: Account<'info, InsuranceVault>,
 
-#[derive(Accounts)]
-pub struct RecordDisasterEvent<'info> {
-    #[account(
-        init_if_needed,
-        payer = authority,
-        space = 8 + 1 + 8 + 8 + 1, 
-        seeds = [b"disaster", region.to_string().as_bytes()],
-        bump
-    )]
-    pub disaster_event: Account<'info, DisasterEvent>,
-    
-    #[account(
-        seeds = [b"config"],
-        bump,
-    )]
-    pub config: Account<'info, InsuranceConfig>,
-    
     #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+    pub provider: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateInsurancePolicy<'info> {
-    #[account(
-        mut,
-        seeds = [b"user", user.key().as_ref()],
-        bump
-    )]
-    pub user_account: AccountLoader<'info, UserAccount>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+pub struct GetTrancheStatus<'info> {
+    #[account(seeds = [b"tranche", &[TrancheKind::Junior as u8]], bump = junior_tranche.bump)]
+    pub junior_tranche: Account<'info, Tranche>,
+
+    #[account(seeds = [b"tranche", &[TrancheKind::Senior as u8]], bump = senior_tranche.bump)]
+    pub senior_tranche: Account<'info, Tranche>,
+}
+
+/// Decimal places magnitudes are stored with.
+pub const MAGNITUDE_DECIMALS: u32 = 2;
+
+/// Supported magnitude range, scaled by 100.
+pub const MIN_MAGNITUDE: u64 = 200;
+pub const MAX_MAGNITUDE: u64 = 900;
+
+/// Formats a magnitude scaled by 100 for logs without going through floats.
+pub struct Magnitude(pub u64);
+
+impl std::fmt::Display for Magnitude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+/// Basis points in 100%, used for payout tiers.
+pub const BASIS_POINTS_DIVISOR: u16 = 10_000;
+
+/// Maximum number of tiers in the payout schedule.
+pub const MAX_PAYOUT_TIERS: usize = 8;
+
+/// Share of `insurance_amount` paid once the magnitude (scaled by 100) is reached.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PayoutTier {
+    pub magnitude: u64,
+    pub payout_bps: u16,
 }
 
 #[account]
 pub struct InsuranceConfig {
-
-    pub threshold_magnitude: u64,
     pub authority: Pubkey,
+    pub payout_tier_count: u8,
+    pub payout_tiers: [PayoutTier; MAX_PAYOUT_TIERS],
+    pub max_feed_staleness_seconds: i64,
+    /// Share of the unused premium withheld when a policy is cancelled
+    pub cancellation_fee_bps: u16,
+    /// Time a submitted claim stays open to disputes
+    pub challenge_window_seconds: i64,
+    /// Lamports a challenger posts to dispute a claim
+    pub dispute_bond: u64,
+    /// Key that resolves disputes
+    pub arbiter: Pubkey,
+    /// Key that can veto queued parameter changes
+    pub guardian: Pubkey,
+    /// Delay between proposing and executing a parameter change
+    pub timelock_delay_seconds: i64,
+    /// Number of parameter changes proposed, also the id of the next one
+    pub change_count: u64,
+    /// Whether claims may be made by transfers signed by a delegate or the
+    /// permanent delegate rather than the policy holder
+    pub allow_delegate_transfers: bool,
 }
 
+/// Program-owned account holding premiums and tranche capital in lamports.
 #[account]
-pub struct RegionFeeds {
-    pub northeast_feed: Pubkey,
-    pub southeast_feed: Pubkey,
-    pub midwest_feed: Pubkey,
-    pub southwest_feed: Pubkey,
-    pub west_feed: Pubkey,
+pub struct InsuranceVault {
+    pub bump: u8,
+    /// Premiums collected since the last distribution to tranches
+    pub undistributed_premiums: u64,
+    /// Cumulative claim losses, the position in the tranche waterfall
+    pub total_losses: u64,
 }
 
-#[account(zero_copy)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrancheKind {
+    Junior,
+    Senior,
+}
+
+impl TrancheKind {
+    pub fn other(self) -> TrancheKind {
+        match self {
+            TrancheKind::Junior => TrancheKind::Senior,
+            TrancheKind::Senior => TrancheKind::Junior,
+        }
+    }
+}
+
+/// Layer of reinsurance capital absorbing claim losses between
+/// `attachment_point` and `attachment_point + capital_deposited`.
+#[account]
+pub struct Tranche {
+    pub kind: TrancheKind,
+    /// Capital provider, who receives the tranche's premium share
+    pub provider: Pubkey,
+    /// Cumulative pool loss at which this tranche starts absorbing
+    pub attachment_point: u64,
+    pub premium_share_bps: u16,
+    pub capital_deposited: u64,
+    pub remaining_capital: u64,
+    pub losses_absorbed: u64,
+    /// Premiums distributed to the tranche and not yet withdrawn
+    pub premiums_owed: u64,
+    pub bump: u8,
+}
+
+impl Tranche {
+    pub fn premium_share_of(&self, premiums: u64) -> u64 {
+        (premiums as u128 * self.premium_share_bps as u128 / BASIS_POINTS_DIVISOR as u128) as u64
+    }
+
+    /// Absorbs the part of a loss taking cumulative losses from
+    /// `cumulative_losses` to `cumulative_losses + loss` that falls in this
+    /// tranche's layer, and returns it.
+    pub fn absorb_loss(&mut self, cumulative_losses: u64, loss: u64) -> u64 {
+        let layer_end = self.attachment_point.saturating_add(self.capital_deposited);
+        let from = cumulative_losses.max(self.attachment_point);
+        let to = cumulative_losses.saturating_add(loss).min(layer_end);
+        let absorbed = to.saturating_sub(from).min(self.remaining_capital);
+
+        self.remaining_capital -= absorbed;
+        self.losses_absorbed += absorbed;
+        absorbed
+    }
+}
+
+/// Bonded challenge to a pending claim, keyed by policy.
+#[account]
+pub struct ClaimDispute {
+    pub policy: Pubkey,
+    pub challenger: Pubkey,
+    pub claim_amount: u64,
+    pub bond: u64,
+    /// Oracle round the challenger says reflects the real quake
+    pub evidence_oracle_round: u64,
+    /// Hash of off-chain evidence
+    pub evidence_hash: [u8; 32],
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct TrancheStatus {
+    pub kind: TrancheKind,
+    pub attachment_point: u64,
+    pub capital_deposited: u64,
+    pub remaining_capital: u64,
+    pub losses_absorbed: u64,
+}
+
+impl InsuranceConfig {
+    /// Payout share of the highest tier reached by `magnitude`, or 0 if none is.
+    pub fn payout_bps_for_magnitude(&self, magnitude: u64) -> u16 {
+        self.payout_tiers[..self.payout_tier_count as usize]
+            .iter()
+            .filter(|tier| magnitude >= tier.magnitude)
+            .map(|tier| tier.payout_bps)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Seconds in a year, the period `premium_rate_bps` is quoted for.
+pub const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+
+/// Seconds in a day, the unit policy durations and extensions are given in.
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Byte offset of `UserAccount::claim_policy_id`, used to resolve the policy in extra account metas.
+pub const USER_CLAIM_POLICY_ID_OFFSET: u8 = 8 + 32 + 8;
+
+/// Byte offset of `UserAccount::claim_policy_registrant`.
+pub const USER_CLAIM_REGISTRANT_OFFSET: u8 = 8 + 32 + 8 + 8;
+
+/// Byte offset of the owner in a token account, used to resolve the policy
+/// holder in extra account metas.
+pub const TOKEN_ACCOUNT_OWNER_OFFSET: u8 = 32;
+
+/// Byte offset of `Policy::zone_id`, used to resolve the zone in extra account metas.
+pub const POLICY_ZONE_ID_OFFSET: u8 = 8 + 32 + 8;
+
+/// Byte offset of `Policy::mint`, used to resolve the holder's policy token account.
+pub const POLICY_MINT_OFFSET: u8 = 8 + 32 + 8 + 8;
+
+/// Byte offset of `CoverageZone::event_count`, used to resolve the latest event in extra account metas.
+pub const ZONE_EVENT_COUNT_OFFSET: u8 = 8 + 8;
+
+/// Epicenter of a quake in microdegrees, with hypocenter depth.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Epicenter {
+    pub latitude: i32,
+    pub longitude: i32,
+    pub depth_km: u32,
+}
+
+/// Coverage area keyed by an H3/geohash cell index or an admin-defined zone id.
+#[account]
+pub struct CoverageZone {
+    pub zone_id: u64,
+    /// Sequence number of the latest disaster event
+    pub event_count: u64,
+    pub feed: Pubkey,
+    /// Magnitude scaled by 100 a quake must reach to pay out in this zone,
+    /// changed through a `ZoneThreshold` proposal
+    pub threshold_magnitude: u64,
+    /// Annual premium as basis points of the insured amount
+    pub premium_rate_bps: u16,
+    /// Oracle round of the latest recorded event
+    pub last_event_round: u64,
+    /// Timestamp of the latest recorded event
+    pub last_event_timestamp: i64,
+    pub is_active: bool,
+    pub bump: u8,
+    /// Optional feeds reporting the latest quake's epicenter and depth
+    pub latitude_feed: Pubkey,
+    pub longitude_feed: Pubkey,
+    pub depth_feed: Pubkey,
+    /// Delay between registration and the start of coverage
+    pub waiting_period_seconds: i64,
+    /// Time after a policy ends during which quakes it covered can still be claimed
+    pub grace_period_seconds: i64,
+    /// Whether the feed was at or above the threshold when last cranked; an
+    /// event is only recorded when a reading rises from below
+    pub above_threshold: bool,
+}
+
+impl CoverageZone {
+    pub fn has_epicenter_feeds(&self) -> bool {
+        self.latitude_feed != Pubkey::default()
+    }
+
+    /// Sequence number the next disaster event for this zone is stored under.
+    pub fn next_event_sequence(&self) -> u64 {
+        self.event_count + 1
+    }
+
+    /// Premium owed for covering `insurance_amount` for `duration_seconds`.
+    pub fn required_premium(&self, insurance_amount: u64, duration_seconds: i64) -> Result<u64> {
+        let premium = (insurance_amount as u128)
+            .checked_mul(self.premium_rate_bps as u128)
+            .and_then(|v| v.checked_mul(duration_seconds.max(0) as u128))
+            .map(|v| v / (BASIS_POINTS_DIVISOR as u128 * SECONDS_PER_YEAR))
+            .ok_or(error!(EarthquakeInsuranceError::InvalidZoneConfig))?;
+
+        u64::try_from(premium).map_err(|_| error!(EarthquakeInsuranceError::InvalidZoneConfig))
+    }
+
+    /// Whether an event has been recorded at or after `timestamp`.
+    pub fn has_event_since(&self, timestamp: i64) -> bool {
+        self.event_count > 0 && self.last_event_timestamp >= timestamp
+    }
+}
+
+/// Per-wallet policy counter.
+#[account]
 pub struct UserAccount {
     pub owner: Pubkey,
-    pub region: u8,
-    pub has_claimed: bool,
+    /// Number of policies registered, also the id of the next one
+    pub policy_count: u64,
+    /// Policy that transfer-hook claims are made against, identified by
+    /// its id and the wallet that registered it
+    pub claim_policy_id: u64,
+    pub claim_policy_registrant: Pubkey,
+}
+
+#[account(zero_copy)]
+pub struct Policy {
+    /// Wallet that registered the policy; claims go to the policy token holder
+    pub registrant: Pubkey,
+    pub policy_id: u64,
+    pub zone_id: u64,
+    /// Token-2022 mint of the transferable policy token
+    pub mint: Pubkey,
     pub insurance_amount: u64,    
     pub premium_paid: u64,       
+    pub claimed_amount: u64,
     pub policy_start_time: i64,   
     pub policy_end_time: i64,    
+    /// Increased coverage bought by an update, applying to quakes from
+    /// `pending_effective_time`; zero when nothing is pending
+    pub pending_insurance_amount: u64,
+    pub pending_effective_time: i64,
+    /// Claim awaiting settlement, payable to `pending_claim_claimant` after
+    /// `claim_challenge_deadline`; zero amount when none is open
+    pub pending_claim_amount: u64,
+    pub pending_claim_claimant: Pubkey,
+    pub pending_claim_event: Pubkey,
+    pub claim_challenge_deadline: i64,
+    /// Insured location in microdegrees
+    pub latitude: i32,
+    pub longitude: i32,
+    /// Disaster event of the latest claim dropped by an upheld dispute, which
+    /// can't be claimed against again
+    pub rejected_claim_event: Pubkey,
 }
 
+impl Policy {
+    /// Amount owed for a payout tier on a quake at `event_timestamp`, before
+    /// deducting earlier claims.
+    pub fn payout_for_bps(&self, payout_bps: u16, event_timestamp: i64) -> u64 {
+        let insured_amount = self.insurance_amount_at(event_timestamp);
+        (insured_amount as u128 * payout_bps as u128 / BASIS_POINTS_DIVISOR as u128) as u64
+    }
+
+    /// Coverage in force for a quake at `timestamp`.
+    pub fn insurance_amount_at(&self, timestamp: i64) -> u64 {
+        if self.pending_insurance_amount > 0 && timestamp >= self.pending_effective_time {
+            self.pending_insurance_amount
+        } else {
+            self.insurance_amount
+        }
+    }
+
+    /// Coverage including any increase still in its waiting period.
+    pub fn latest_insurance_amount(&self) -> u64 {
+        self.insurance_amount.max(self.pending_insurance_amount)
+    }
+
+    /// Folds a pending increase into `insurance_amount` once it is in force.
+    pub fn settle_pending_coverage(&mut self, now: i64) {
+        if self.pending_insurance_amount > 0 && now >= self.pending_effective_time {
+            self.insurance_amount = self.pending_insurance_amount;
+            self.pending_insurance_amount = 0;
+            self.pending_effective_time = 0;
+        }
+    }
+
+    pub fn clear_pending_claim(&mut self) {
+        self.pending_claim_amount = 0;
+        self.pending_claim_claimant = Pubkey::default();
+        self.pending_claim_event = Pubkey::default();
+        self.claim_challenge_deadline = 0;
+    }
+
+    /// Unused share of the premium at `now`, less a cancellation fee.
+    pub fn cancellation_refund(&self, now: i64, cancellation_fee_bps: u16) -> u64 {
+        let term = (self.policy_end_time - self.policy_start_time).max(1) as u128;
+        let unused = (self.policy_end_time - now.max(self.policy_start_time)).clamp(0, term as i64) as u128;
+
+        let unused_premium = self.premium_paid as u128 * unused / term;
+        let fee = unused_premium * cancellation_fee_bps as u128 / BASIS_POINTS_DIVISOR as u128;
+        (unused_premium - fee) as u64
+    }
+}
 
 #[account]
 pub struct DisasterEvent {
-    pub region: u8,             
+    pub zone_id: u64,
+    pub sequence: u64,
     pub magnitude: u64,          
     pub timestamp: i64,           
+    pub oracle_round: u64,
     pub verified: bool,           
-} 
\ No newline at end of file
+    /// Magnitude was entered by the authority instead of read from the feed
+    pub manual_override: bool,
+    /// Epicenter in microdegrees and hypocenter depth, when reported
+    pub epicenter_latitude: i32,
+    pub epicenter_longitude: i32,
+    pub depth_km: u32,
+    pub has_epicenter: bool,
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // M7.00 in hundredths
+    const THRESHOLD: u64 = 700;
+
+    fn decimal(mantissa: i128, scale: u32) -> SwitchboardDecimal {
+        SwitchboardDecimal { mantissa, scale }
+    }
+
+    fn clears_threshold(value: SwitchboardDecimal) -> bool {
+        magnitude_from_decimal(&value).unwrap() >= THRESHOLD
+    }
+
+    #[test]
+    fn scale_two_matches_hundredths() {
+        assert_eq!(rescale_magnitude(&decimal(699, 2)), Some(699));
+        assert_eq!(rescale_magnitude(&decimal(700, 2)), Some(700));
+        assert_eq!(rescale_magnitude(&decimal(701, 2)), Some(701));
+
+        assert!(!clears_threshold(decimal(699, 2)));
+        assert!(clears_threshold(decimal(700, 2)));
+        assert!(clears_threshold(decimal(701, 2)));
+    }
+
+    #[test]
+    fn scale_eighteen_rounds_down_to_hundredths() {
+        let hundredth = 10i128.pow(16);
+        assert_eq!(rescale_magnitude(&decimal(699 * hundredth, 18)), Some(699));
+        assert_eq!(rescale_magnitude(&decimal(700 * hundredth, 18)), Some(700));
+        assert_eq!(rescale_magnitude(&decimal(701 * hundredth, 18)), Some(701));
+
+        assert!(!clears_threshold(decimal(699 * hundredth, 18)));
+        assert!(clears_threshold(decimal(700 * hundredth, 18)));
+        assert!(clears_threshold(decimal(701 * hundredth, 18)));
+
+        // 6.999999999999999999 must not round up to the threshold
+        assert_eq!(rescale_magnitude(&decimal(700 * hundredth - 1, 18)), Some(699));
+        assert!(!clears_threshold(decimal(700 * hundredth - 1, 18)));
+    }
+
+    #[test]
+    fn scale_zero_scales_up_whole_magnitudes() {
+        // Whole magnitudes can't express a hundredth either side of the
+        // threshold, so the neighbours are a full magnitude away
+        assert_eq!(rescale_magnitude(&decimal(6, 0)), Some(600));
+        assert_eq!(rescale_magnitude(&decimal(7, 0)), Some(700));
+        assert_eq!(rescale_magnitude(&decimal(8, 0)), Some(800));
+
+        assert!(!clears_threshold(decimal(6, 0)));
+        assert!(clears_threshold(decimal(7, 0)));
+        assert!(clears_threshold(decimal(8, 0)));
+    }
+
+    #[test]
+    fn rejects_negative_and_overflowing_readings() {
+        assert_eq!(rescale_magnitude(&decimal(-700, 2)), None);
+        assert_eq!(rescale_magnitude(&decimal(i128::MAX, 0)), None);
+        assert!(magnitude_from_decimal(&decimal(-700, 2)).is_err());
+    }
+
+    #[test]
+    fn policy_durations_must_be_positive_and_fit_a_timestamp() {
+        assert_eq!(policy_duration_seconds(1).unwrap(), SECONDS_PER_DAY);
+        assert_eq!(policy_duration_seconds(365).unwrap(), 365 * SECONDS_PER_DAY);
+        assert!(policy_duration_seconds(0).is_err());
+        assert!(policy_duration_seconds((i64::MAX / SECONDS_PER_DAY) as u64 + 1).is_err());
+        assert!(policy_duration_seconds(u64::MAX).is_err());
+    }
+
+    fn tranche(kind: TrancheKind, attachment_point: u64, capital: u64) -> Tranche {
+        Tranche {
+            kind,
+            provider: Pubkey::new_unique(),
+            attachment_point,
+            premium_share_bps: 0,
+            capital_deposited: capital,
+            remaining_capital: capital,
+            losses_absorbed: 0,
+            premiums_owed: 0,
+            bump: 255,
+        }
+    }
+
+    fn vault(total_losses: u64) -> InsuranceVault {
+        InsuranceVault { bump: 255, undistributed_premiums: 0, total_losses }
+    }
+
+    // The pool retains the first 100, the junior layer covers 100..300 and
+    // the senior layer 300..800
+    fn layers() -> (Tranche, Tranche) {
+        (tranche(TrancheKind::Junior, 100, 200), tranche(TrancheKind::Senior, 300, 500))
+    }
+
+    #[test]
+    fn losses_inside_the_junior_layer_leave_the_senior_untouched() {
+        let (mut junior, mut senior) = layers();
+        let mut vault = vault(0);
+
+        // Within the retention nothing is absorbed
+        absorb_claim_loss(&mut vault, Some(&mut junior), Some(&mut senior), 80).unwrap();
+        assert_eq!((junior.losses_absorbed, senior.losses_absorbed), (0, 0));
+
+        absorb_claim_loss(&mut vault, Some(&mut junior), Some(&mut senior), 70).unwrap();
+        assert_eq!(junior.losses_absorbed, 50);
+        assert_eq!(junior.remaining_capital, 150);
+        assert_eq!(senior.remaining_capital, 500);
+        assert_eq!(vault.total_losses, 150);
+    }
+
+    #[test]
+    fn losses_spanning_both_layers_are_split_at_the_attachment_point() {
+        let (mut junior, mut senior) = layers();
+        let mut vault = vault(250);
+        junior.remaining_capital = 50;
+        junior.losses_absorbed = 150;
+
+        absorb_claim_loss(&mut vault, Some(&mut junior), Some(&mut senior), 200).unwrap();
+        assert_eq!((junior.remaining_capital, junior.losses_absorbed), (0, 200));
+        assert_eq!((senior.remaining_capital, senior.losses_absorbed), (350, 150));
+        assert_eq!(vault.total_losses, 450);
+    }
+
+    #[test]
+    fn losses_beyond_total_capital_exhaust_both_layers() {
+        let (mut junior, mut senior) = layers();
+        let mut vault = vault(0);
+
+        absorb_claim_loss(&mut vault, Some(&mut junior), Some(&mut senior), 2_000).unwrap();
+        assert_eq!((junior.remaining_capital, junior.losses_absorbed), (0, 200));
+        assert_eq!((senior.remaining_capital, senior.losses_absorbed), (0, 500));
+        assert_eq!(vault.total_losses, 2_000);
+
+        // Later losses are retained by the pool
+        absorb_claim_loss(&mut vault, Some(&mut junior), Some(&mut senior), 100).unwrap();
+        assert_eq!((junior.losses_absorbed, senior.losses_absorbed), (200, 500));
+        assert_eq!(vault.total_losses, 2_100);
+
+        // Without tranches the pool retains everything
+        absorb_claim_loss(&mut vault, None, None, 100).unwrap();
+        assert_eq!(vault.total_losses, 2_200);
+    }
+
+    fn check_layers(tranche: &Tranche, other: Option<&Tranche>) -> Result<()> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = Vec::new();
+        if let Some(other) = other {
+            other.try_serialize(&mut data).unwrap();
+        }
+        let owner = if other.is_some() { crate::ID } else { system_program::ID };
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        check_tranche_layers(tranche, &account)
+    }
+
+    #[test]
+    fn senior_layer_must_attach_above_the_junior_layer() {
+        let (junior, senior) = layers();
+        // Adjacent layers pass from either side
+        assert!(check_layers(&junior, Some(&senior)).is_ok());
+        assert!(check_layers(&senior, Some(&junior)).is_ok());
+        // Until the other tranche exists there is nothing to overlap
+        assert!(check_layers(&junior, None).is_ok());
+
+        // Junior capital growing past the senior attachment point overlaps
+        let mut grown = junior.clone();
+        grown.capital_deposited = 201;
+        assert!(check_layers(&grown, Some(&senior)).is_err());
+        assert!(check_layers(&senior, Some(&grown)).is_err());
+
+        // As does a senior layer attaching below the junior one
+        let low_senior = tranche(TrancheKind::Senior, 50, 500);
+        assert!(check_layers(&low_senior, Some(&junior)).is_err());
+
+        let overflowing = tranche(TrancheKind::Junior, u64::MAX, 1);
+        assert!(check_layers(&overflowing, Some(&senior)).is_err());
+    }
+
+    // A 1_000 premium for a 100 second term starting at 1_000
+    fn policy() -> Policy {
+        Policy {
+            premium_paid: 1_000,
+            policy_start_time: 1_000,
+            policy_end_time: 1_100,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+
+    #[test]
+    fn cancellation_refunds_the_unused_premium_less_the_fee() {
+        let policy = policy();
+        // At or before the start the whole premium is unused
+        assert_eq!(policy.cancellation_refund(1_000, 0), 1_000);
+        assert_eq!(policy.cancellation_refund(500, 0), 1_000);
+        assert_eq!(policy.cancellation_refund(1_000, 500), 950);
+
+        // Halfway through half of it is
+        assert_eq!(policy.cancellation_refund(1_050, 0), 500);
+        assert_eq!(policy.cancellation_refund(1_050, 500), 475);
+
+        // Nothing is left after the end
+        assert_eq!(policy.cancellation_refund(1_100, 0), 0);
+        assert_eq!(policy.cancellation_refund(2_000, 500), 0);
+    }
+
+    #[test]
+    fn cancellation_fee_never_exceeds_the_unused_premium() {
+        let policy = policy();
+        // The fee is a share of the unused premium, so the largest one takes
+        // all of it without underflowing
+        assert_eq!(policy.cancellation_refund(1_000, 10_000), 0);
+        assert_eq!(policy.cancellation_refund(1_099, 10_000), 0);
+        assert_eq!(policy.cancellation_refund(1_099, 9_999), 1);
+        assert_eq!(policy.cancellation_refund(1_099, 5_000), 5);
+    }
+
+    #[test]
+    fn refunds_come_from_undistributed_premiums_first() {
+        let (mut junior, mut senior) = layers();
+        junior.premium_share_bps = 2_000;
+        senior.premium_share_bps = 3_000;
+        junior.premiums_owed = 200;
+        senior.premiums_owed = 300;
+        let mut vault = vault(0);
+        vault.undistributed_premiums = 400;
+
+        return_refunded_premiums(&mut vault, Some(&mut junior), Some(&mut senior), 300).unwrap();
+        assert_eq!(vault.undistributed_premiums, 100);
+        assert_eq!((junior.premiums_owed, senior.premiums_owed), (200, 300));
+
+        // Past them each tranche gives back its share of the rest and the
+        // pool covers its retained half
+        return_refunded_premiums(&mut vault, Some(&mut junior), Some(&mut senior), 1_100).unwrap();
+        assert_eq!(vault.undistributed_premiums, 0);
+        assert_eq!((junior.premiums_owed, senior.premiums_owed), (0, 0));
+
+        // Without tranches the pool covers everything
+        return_refunded_premiums(&mut vault, None, None, 100).unwrap();
+        assert_eq!(vault.undistributed_premiums, 0);
+    }
+
+    #[test]
+    fn refunds_fail_once_a_tranche_withdrew_its_premiums() {
+        let (mut junior, mut senior) = layers();
+        junior.premium_share_bps = 2_000;
+        senior.premium_share_bps = 3_000;
+        senior.premiums_owed = 300;
+        let mut vault = vault(0);
+
+        assert!(return_refunded_premiums(&mut vault, Some(&mut junior), Some(&mut senior), 100).is_err());
+        // A share that rounds to nothing can still be refunded
+        assert!(return_refunded_premiums(&mut vault, Some(&mut junior), Some(&mut senior), 4).is_ok());
+    }
+
+    fn tier(magnitude: u64, payout_bps: u16) -> PayoutTier {
+        PayoutTier { magnitude, payout_bps }
+    }
+
+    // The README's example schedule: M6.0 pays 25%, M7.0 60%, M8.0 100%
+    fn tiered_config() -> InsuranceConfig {
+        let tiers = [tier(600, 2_500), tier(700, 6_000), tier(800, 10_000)];
+        let mut payout_tiers = [PayoutTier::default(); MAX_PAYOUT_TIERS];
+        payout_tiers[..tiers.len()].copy_from_slice(&tiers);
+        InsuranceConfig {
+            authority: Pubkey::new_unique(),
+            payout_tier_count: tiers.len() as u8,
+            payout_tiers,
+            max_feed_staleness_seconds: 0,
+            cancellation_fee_bps: 0,
+            challenge_window_seconds: 0,
+            dispute_bond: 0,
+            arbiter: Pubkey::new_unique(),
+            guardian: Pubkey::new_unique(),
+            timelock_delay_seconds: 0,
+            change_count: 0,
+            allow_delegate_transfers: false,
+        }
+    }
+
+    #[test]
+    fn magnitudes_on_a_threshold_reach_its_tier() {
+        let config = tiered_config();
+        assert_eq!(config.payout_bps_for_magnitude(600), 2_500);
+        assert_eq!(config.payout_bps_for_magnitude(699), 2_500);
+        assert_eq!(config.payout_bps_for_magnitude(700), 6_000);
+        assert_eq!(config.payout_bps_for_magnitude(799), 6_000);
+        assert_eq!(config.payout_bps_for_magnitude(800), 10_000);
+        assert_eq!(config.payout_bps_for_magnitude(MAX_MAGNITUDE), 10_000);
+    }
+
+    #[test]
+    fn magnitudes_below_the_lowest_tier_pay_nothing() {
+        let config = tiered_config();
+        assert_eq!(config.payout_bps_for_magnitude(599), 0);
+        assert_eq!(config.payout_bps_for_magnitude(0), 0);
+
+        // Tiers past the count are ignored
+        let mut config = tiered_config();
+        config.payout_tier_count = 1;
+        assert_eq!(config.payout_bps_for_magnitude(800), 2_500);
+    }
+}
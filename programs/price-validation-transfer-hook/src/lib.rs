@@ -1,13 +1,23 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 use anchor_lang::solana_program::account_info::AccountInfo;
 use anchor_lang::solana_program::system_instruction;
-use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::solana_program::pubkey::Pubkey;
 use anchor_lang::solana_program::sysvar::{Sysvar, rent::Rent};
-use anchor_lang::solana_program::program_error::ProgramError;
-use std::str::FromStr;
-use spl_transfer_hook_interface::instruction::TransferHookInstruction;
-use switchboard_v2::{AggregatorAccountData, SwitchboardDecimal};
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{
+        interest_bearing_mint::InterestBearingConfig,
+        transfer_fee::TransferFeeConfig,
+        transfer_hook::{TransferHook as TransferHookExtension, TransferHookAccount},
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    state::{Account as TokenAccountState, Mint as MintState},
+};
+use anchor_spl::token_interface::{self, TokenAccount};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+use switchboard_solana::AggregatorAccountData;
 
 declare_id!("BVXu4oZsj9EHbthGov1ygmVx333cUoT1HaiD6DJS7aph");
 
@@ -21,6 +31,44 @@ pub enum PriceValidationError {
     SwitchboardError,
     #[msg("Price validation is not active")]
     PriceValidationNotActive,
+    #[msg("Price feed does not match the configured feed")]
+    InvalidPriceFeed,
+    #[msg("Extra account meta list does not belong to the mint")]
+    InvalidExtraAccountMetaList,
+    #[msg("Mint does not use this program as its transfer hook")]
+    InvalidHookProgram,
+    #[msg("Signer is not the mint's transfer hook authority")]
+    UnauthorizedHookAuthority,
+    #[msg("Extra account meta list already exists")]
+    ExtraAccountMetaListExists,
+    #[msg("Only the program upgrade authority can initialize the state")]
+    UnauthorizedInitializer,
+    #[msg("Signer is not the state authority")]
+    Unauthorized,
+    #[msg("Transfer hook was not invoked by a Token-2022 transfer of this mint")]
+    NotTransferring,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Merchant is suspended")]
+    MerchantSuspended,
+    #[msg("Payments to a merchant must go to its payout token account")]
+    MerchantPayoutAccountMismatch,
+    #[msg("Merchant sells no product priced by the configured feeds")]
+    ProductNotListed,
+    #[msg("Invalid merchant configuration")]
+    InvalidMerchantConfig,
+    #[msg("Transfer exceeds the maximum amount for this mint")]
+    TransferAmountLimitExceeded,
+    #[msg("Transfer exceeds the owner's rolling 24h volume for this mint")]
+    DailyVolumeLimitExceeded,
+    #[msg("Owner has no usage account for this mint")]
+    UsageAccountMissing,
+    #[msg("Invalid product price feeds")]
+    InvalidProductFeeds,
+    #[msg("Mint has an extension that changes its UI amounts in an unsupported way")]
+    UnsupportedMintExtension,
+    #[msg("State account is not in a layout this version can migrate")]
+    UnsupportedStateLayout,
 }
 
 
@@ -31,35 +79,48 @@ pub mod price_validation_transfer_hook {
 
     pub fn initialize_state(
         ctx: Context<InitializeState>,
+        product_price_feeds: Vec<Pubkey>,
         tolerance_basis_points: u64,
-        is_price_validation_active: bool
+        is_price_validation_active: bool,
+        price_covered_by_net_amount: bool
     ) -> Result<()> {
+        check_product_price_feeds_config(&product_price_feeds)?;
+
         let state = &mut ctx.accounts.state;
-        state.product_price_feed_pubkey = ctx.accounts.product_price_feed.key();
+        state.authority = ctx.accounts.payer.key();
+        state.product_price_feeds = product_price_feeds;
         state.token_usd_price_feed_pubkey = ctx.accounts.token_usd_price_feed.key();
         state.tolerance_basis_points = tolerance_basis_points;
         state.is_price_validation_active = is_price_validation_active;
+        state.price_covered_by_net_amount = price_covered_by_net_amount;
+        state.version = STATE_VERSION;
 
-        msg!("State initialized with product price feed: {}", state.product_price_feed_pubkey);
+        msg!("State initialized with {} product price feeds", state.product_price_feeds.len());
         msg!("State initialized with token/USD price feed: {}", state.token_usd_price_feed_pubkey);
         msg!("Tolerance basis points: {}", state.tolerance_basis_points);
         msg!("Price validation active: {}", state.is_price_validation_active);
+        msg!("Price covered by net amount: {}", state.price_covered_by_net_amount);
+        msg!("Authority: {}", state.authority);
 
         Ok(())
     }
 
+    /// Feed changes reach a mint once its transfer hook authority rewrites the
+    /// mint's list with `update_extra_account_meta_list`.
     pub fn update_price_validation_config(
         ctx: Context<UpdateConfig>,
-        product_price_feed_pubkey: Option<Pubkey>,
+        product_price_feeds: Option<Vec<Pubkey>>,
         token_usd_price_feed_pubkey: Option<Pubkey>,
         is_active: Option<bool>,
         tolerance_basis_points: Option<u64>,
+        price_covered_by_net_amount: Option<bool>,
     ) -> Result<()> {
         let state = &mut ctx.accounts.state;
         
-        if let Some(feed) = product_price_feed_pubkey {
-            state.product_price_feed_pubkey = feed;
-            msg!("Updated product price feed: {}", feed);
+        if let Some(feeds) = product_price_feeds {
+            check_product_price_feeds_config(&feeds)?;
+            msg!("Updated product price feeds: {} feeds", feeds.len());
+            state.product_price_feeds = feeds;
         }
         
         if let Some(feed) = token_usd_price_feed_pubkey {
@@ -76,153 +137,193 @@ pub mod price_validation_transfer_hook {
             state.tolerance_basis_points = tolerance;
             msg!("Updated tolerance basis points: {}", tolerance);
         }
-        
+
+        if let Some(net) = price_covered_by_net_amount {
+            state.price_covered_by_net_amount = net;
+            msg!("Updated price covered by net amount: {}", net);
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites a state account created by an earlier deployment in the
+    /// current layout. Its single product feed becomes the product feed list,
+    /// and fields it predates get their defaults: the signer as authority and
+    /// prices covered by the amount sent. Mint lists must then be rewritten
+    /// with `update_extra_account_meta_list`.
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let state_info = &ctx.accounts.state;
+        let state = {
+            let data = state_info.try_borrow_data()?;
+            read_legacy_state(&data, ctx.accounts.authority.key())?
+        };
+
+        resize_program_account(
+            state_info,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            STATE_SIZE,
+        )?;
+        let mut data = state_info.try_borrow_mut_data()?;
+        data.fill(0);
+        state.try_serialize(&mut &mut data[..])?;
+
+        msg!("State migrated to version {} with authority {}", state.version, state.authority);
         Ok(())
     }
 
     pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
-        let (state_pubkey, _) = Pubkey::find_program_address(&[b"state_v3"], ctx.program_id);
         msg!("Initializing extra account meta list for mint: {}", ctx.accounts.mint.key);
-        msg!("State PDA: {}", state_pubkey);
-        
-        // Lấy địa chỉ của hai feed giá
-        let (product_price_feed_pubkey, _) = Pubkey::find_program_address(&[b"product_price_feed"], ctx.program_id);
-        let (token_usd_price_feed_pubkey, _) = Pubkey::find_program_address(&[b"token_usd_price_feed"], ctx.program_id);
-        
-        msg!("Product price feed PDA: {}", product_price_feed_pubkey);
-        msg!("Token/USD price feed PDA: {}", token_usd_price_feed_pubkey);
+
+        check_transfer_hook_authority(ctx.program_id, &ctx.accounts.mint, ctx.accounts.authority.key)?;
+
+        // Existing lists can only be changed through update_extra_account_meta_list
+        if ctx.accounts.extra_account_metas.owner == ctx.program_id {
+            return Err(PriceValidationError::ExtraAccountMetaListExists.into());
+        }
         
-        let extra_account_metas = vec![
-            (
-                state_pubkey, 
-                false,        
-                false,        
-            ),
-            (
-                product_price_feed_pubkey,
-                false,            
-                false,            
-            ),
-            (
-                token_usd_price_feed_pubkey,
-                false,            
-                false,            
-            ),
+        let extra_account_metas = price_extra_account_metas(&ctx.accounts.state)?;
+        let account_metas_size = ExtraAccountMetaList::size_of(extra_account_metas.len())?;
+        let mint_key = ctx.accounts.mint.key();
+        let signer_seeds: &[&[u8]] = &[
+            b"extra-account-metas",
+            mint_key.as_ref(),
+            &[ctx.bumps.extra_account_metas],
         ];
-        
-        let account_metas_size = 1 + extra_account_metas.len() * 34; 
-        
-        if ctx.accounts.extra_account_metas.owner != ctx.program_id {
-            let rent = Rent::get()?;
-            let lamports = rent.minimum_balance(account_metas_size);
-            
-            msg!("Creating account with {} lamports for rent exemption", lamports);
-            invoke(
-                &system_instruction::create_account(
-                    ctx.accounts.authority.key,
-                    ctx.accounts.extra_account_metas.key,
-                    lamports,
-                    account_metas_size as u64,
-                    ctx.program_id,
-                ),
-                &[
-                    ctx.accounts.authority.to_account_info(),
-                    ctx.accounts.extra_account_metas.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-            msg!("Account created successfully");
-        }        
-        msg!("Initializing extra account meta data");
-
-        let mut data = ctx.accounts.extra_account_metas.try_borrow_mut_data()?;
-        data.fill(0);
-        data[0] = extra_account_metas.len() as u8;
-        msg!("Number of extra account metas: {}", data[0]);
-        let mut offset = 1;
-        for (pubkey, is_signer, is_writable) in extra_account_metas {
-            data[offset..offset+32].copy_from_slice(pubkey.as_ref());
-            data[offset+32] = is_signer as u8;
-            data[offset+33] = is_writable as u8;
-            msg!("Added extra account meta: {}", pubkey);
-            msg!("is_signer: {}, is_writable: {}", is_signer, is_writable);
-            offset += 34; 
-        }   
-        msg!("Extra account meta list initialized successfully");
+
+        create_program_account(
+            &ctx.accounts.extra_account_metas,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.program_id,
+            signer_seeds,
+            account_metas_size,
+        )?;
+        msg!("Account created successfully");
+
+        write_extra_account_metas(
+            ctx.program_id,
+            &ctx.accounts.mint,
+            &ctx.accounts.extra_account_metas,
+            &extra_account_metas,
+        )?;
+        msg!("Extra account meta list initialized with {} accounts", extra_account_metas.len());
+        Ok(())
+    }
+
+    /// Rewrites a mint's extra account meta list with the feeds currently in the state.
+    pub fn update_extra_account_meta_list(ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+        check_transfer_hook_authority(ctx.program_id, &ctx.accounts.mint, ctx.accounts.authority.key)?;
+
+        // Lists created before the hook needed more accounts are resized in place
+        let extra_account_metas = price_extra_account_metas(&ctx.accounts.state)?;
+        resize_program_account(
+            &ctx.accounts.extra_account_metas,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ExtraAccountMetaList::size_of(extra_account_metas.len())?,
+        )?;
+
+        write_extra_account_metas(
+            ctx.program_id,
+            &ctx.accounts.mint,
+            &ctx.accounts.extra_account_metas,
+            &extra_account_metas,
+        )?;
+        msg!("Extra account meta list updated for mint {} with {} accounts",
+             ctx.accounts.mint.key, extra_account_metas.len());
+        Ok(())
+    }
+
+    /// Registers a merchant; transfers to it are validated against its products.
+    pub fn onboard_merchant(
+        ctx: Context<OnboardMerchant>,
+        merchant_owner: Pubkey,
+        product_price_feeds: Vec<Pubkey>,
+    ) -> Result<()> {
+        if product_price_feeds.is_empty() || product_price_feeds.len() > MAX_MERCHANT_PRODUCTS {
+            return Err(PriceValidationError::InvalidMerchantConfig.into());
+        }
+
+        let merchant = &mut ctx.accounts.merchant;
+        merchant.owner = merchant_owner;
+        merchant.payout_token_account = ctx.accounts.payout_token_account.key();
+        merchant.product_price_feeds = product_price_feeds;
+        merchant.is_suspended = false;
+        merchant.bump = ctx.bumps.merchant;
+
+        msg!("Merchant {} onboarded with payout account {}", merchant_owner, merchant.payout_token_account);
+        msg!("Products: {}", merchant.product_price_feeds.len());
+        Ok(())
+    }
+
+    /// Suspends or reinstates a merchant. Transfers to a suspended merchant are rejected.
+    pub fn suspend_merchant(
+        ctx: Context<SuspendMerchant>,
+        merchant_owner: Pubkey,
+        is_suspended: bool,
+    ) -> Result<()> {
+        ctx.accounts.merchant.is_suspended = is_suspended;
+        msg!("Merchant {} suspended: {}", merchant_owner, is_suspended);
+        Ok(())
+    }
+
+    /// Sets purchase limits for a mint; zero disables a limit. Only payments to
+    /// merchants are limited, other transfers of the mint are not counted.
+    pub fn set_mint_limits(
+        ctx: Context<SetMintLimits>,
+        max_transfer_amount: u64,
+        max_daily_volume: u64,
+    ) -> Result<()> {
+        let limits = &mut ctx.accounts.limits;
+        limits.mint = ctx.accounts.mint.key();
+        limits.max_transfer_amount = max_transfer_amount;
+        limits.max_daily_volume = max_daily_volume;
+        limits.bump = ctx.bumps.limits;
+
+        msg!("Limits for mint {}: max transfer {}, max 24h volume {}",
+             limits.mint, max_transfer_amount, max_daily_volume);
+        Ok(())
+    }
+
+    /// Creates the account tracking an owner's purchase volume for a mint.
+    /// Required before buying from merchants when the mint has a volume cap.
+    pub fn initialize_usage(ctx: Context<InitializeUsage>) -> Result<()> {
+        let usage = &mut ctx.accounts.usage;
+        usage.owner = ctx.accounts.owner.key();
+        usage.mint = ctx.accounts.mint.key();
+        usage.hourly_volume = [0; USAGE_WINDOW_HOURS];
+        usage.last_hour = Clock::get()?.unix_timestamp / SECONDS_PER_HOUR;
+        usage.bump = ctx.bumps.usage;
+
+        msg!("Usage account created for {} on mint {}", usage.owner, usage.mint);
         Ok(())
     }
 
     pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
         msg!("Transfer hook executing with amount: {}", amount);
-        
-        let state = &ctx.accounts.state;
-        
-    
-        if !state.is_price_validation_active {
-            msg!("Price validation is not active, allowing transfer");
-            return Ok(());
-        }
-        
-     
-        let product_price_feed = &ctx.accounts.product_price_feed;
-        let product_feed_data = match AggregatorAccountData::new(product_price_feed) {
-            Ok(data) => data,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
-        
-        let product_price_result = match product_feed_data.get_result() {
-            Ok(result) => result,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
-        
-        let product_price = product_price_result.mantissa / 10u128.pow(product_price_result.scale as u32);
-        let product_price = match u64::try_from(product_price) {
-            Ok(price) => price,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
-        
-        
-        let token_usd_price_feed = &ctx.accounts.token_usd_price_feed;
-        let token_usd_feed_data = match AggregatorAccountData::new(token_usd_price_feed) {
-            Ok(data) => data,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
-        
-        let token_usd_price_result = match token_usd_feed_data.get_result() {
-            Ok(result) => result,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
-        
-        let token_usd_price = token_usd_price_result.mantissa / 10u128.pow(token_usd_price_result.scale as u32);
-        let token_usd_price = match u64::try_from(token_usd_price) {
-            Ok(price) => price,
-            Err(_) => return Err(PriceValidationError::SwitchboardError.into()),
-        };
 
-        msg!("Product price from Switchboard: {}", product_price);
-        msg!("Token/USD price from Switchboard: {}", token_usd_price);
+        check_is_transferring(
+            &ctx.accounts.source.to_account_info(),
+            &ctx.accounts.mint,
+            &ctx.accounts.destination.to_account_info(),
+        )?;
 
-       
-        let expected_token_amount = if token_usd_price > 0 {
-            (product_price * 1_000_000_000) / token_usd_price
-        } else {
-            return Err(PriceValidationError::SwitchboardError.into());
+        let merchant = match merchant_payment(&ctx.accounts.merchant, &ctx.accounts.destination.key())? {
+            Some(merchant) => merchant,
+            None => return Ok(()),
         };
-        
-        let deviation_amount = (expected_token_amount * state.tolerance_basis_points) / 10000;
-        let min_acceptable_amount = expected_token_amount.saturating_sub(deviation_amount);
-        let max_acceptable_amount = expected_token_amount.saturating_add(deviation_amount);
+        check_price_feeds(&ctx.accounts.state, &ctx.accounts.token_usd_price_feed, ctx.remaining_accounts)?;
 
-        msg!("Expected token amount: {}", expected_token_amount);
-        msg!("Acceptable amount range: [{}, {}]", min_acceptable_amount, max_acceptable_amount);
+        enforce_transfer_limits(&ctx.accounts.limits, &ctx.accounts.usage, amount)?;
 
-        if amount >= min_acceptable_amount && amount <= max_acceptable_amount {
-            msg!("Valid amount ({}), allowing token transfer", amount);
-            Ok(())
-        } else {
-            msg!("Invalid amount ({}), rejecting token transfer", amount);
-            Err(PriceValidationError::PriceOutOfRange.into())
-        }
+        validate_transfer_amount(
+            &ctx.accounts.state,
+            &ctx.accounts.mint,
+            &merchant_price_feeds(&merchant, ctx.remaining_accounts)?,
+            &ctx.accounts.token_usd_price_feed,
+            amount,
+        )
     }
 
     pub fn fallback<'info>(
@@ -244,131 +345,676 @@ pub mod price_validation_transfer_hook {
             TransferHookInstruction::Execute { amount } => {
                 msg!("Execute with amount: {}", amount);
                 
-                if accounts.len() < 8 {
+                if accounts.len() < 10 {
                     msg!("Not enough accounts provided");
                     return Err(PriceValidationError::InvalidInstruction.into());
                 }
-                
+
+                check_is_transferring(&accounts[0], &accounts[1], &accounts[2])?;
+
                 let state_account = &accounts[5];
-                let product_price_feed_account = &accounts[6];
-                let token_usd_price_feed_account = &accounts[7];
-                
-                let state_data = state_account.try_borrow_data()?;
-                if state_data.len() < 8 + 32 + 32 + 8 + 1 { 
-                    msg!("Invalid state account data");
+                let token_usd_price_feed_account = &accounts[6];
+                let merchant_account = &accounts[7];
+                let limits_account = &accounts[8];
+                let usage_account = &accounts[9];
+                let product_price_feed_accounts = &accounts[10..];
+
+                let (state_pubkey, _) = Pubkey::find_program_address(&[b"state_v3"], program_id);
+                if state_account.key() != state_pubkey {
+                    msg!("Invalid state account");
                     return Err(PriceValidationError::InvalidInstruction.into());
                 }
-                
-                let is_validation_active = state_data[72] != 0;
-                if !is_validation_active {
-                    msg!("Price validation is not active, allowing transfer");
-                    return Ok(());
+                let state = Account::<PriceValidationState>::try_from(state_account)?;
+
+                let destination = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+                let (merchant_pubkey, _) =
+                    Pubkey::find_program_address(&[b"merchant", destination.owner.as_ref()], program_id);
+                if merchant_account.key() != merchant_pubkey {
+                    msg!("Invalid merchant account");
+                    return Err(PriceValidationError::InvalidInstruction.into());
                 }
-                
-                let tolerance_basis_points = u64::from_le_bytes([
-                    state_data[64], state_data[65], state_data[66], state_data[67],
-                    state_data[68], state_data[69], state_data[70], state_data[71],
-                ]);
-                
-                let product_feed_data = match AggregatorAccountData::new(product_price_feed_account) {
-                    Ok(data) => data,
-                    Err(_) => {
-                        msg!("Failed to read product price aggregator data");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
+                let merchant = match merchant_payment(merchant_account, &destination.key())? {
+                    Some(merchant) => merchant,
+                    None => return Ok(()),
                 };
-                
-                let product_price_result = match product_feed_data.get_result() {
-                    Ok(result) => result,
-                    Err(_) => {
-                        msg!("Failed to get result from product price aggregator");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
-                };
-                
-                let product_price = product_price_result.mantissa / 10u128.pow(product_price_result.scale as u32);
-                let product_price = match u64::try_from(product_price) {
-                    Ok(price) => price,
-                    Err(_) => {
-                        msg!("Failed to convert product price to u64");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
-                };
-                
-                let token_usd_feed_data = match AggregatorAccountData::new(token_usd_price_feed_account) {
-                    Ok(data) => data,
-                    Err(_) => {
-                        msg!("Failed to read token/USD price aggregator data");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
-                };
-                
-                let token_usd_price_result = match token_usd_feed_data.get_result() {
-                    Ok(result) => result,
-                    Err(_) => {
-                        msg!("Failed to get result from token/USD price aggregator");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
-                };
-                
-                let token_usd_price = token_usd_price_result.mantissa / 10u128.pow(token_usd_price_result.scale as u32);
-                let token_usd_price = match u64::try_from(token_usd_price) {
-                    Ok(price) => price,
-                    Err(_) => {
-                        msg!("Failed to convert token/USD price to u64");
-                        return Err(PriceValidationError::SwitchboardError.into());
-                    }
-                };
-                
-                msg!("Product price from Switchboard: {}", product_price);
-                msg!("Token/USD price from Switchboard: {}", token_usd_price);
-                
-                let expected_token_amount = if token_usd_price > 0 {
-                    (product_price * 1_000_000_000) / token_usd_price
-                } else {
-                    return Err(PriceValidationError::SwitchboardError.into());
-                };
-                
-                let deviation_amount = (expected_token_amount * tolerance_basis_points) / 10000;
-                let min_acceptable_amount = expected_token_amount.saturating_sub(deviation_amount);
-                let max_acceptable_amount = expected_token_amount.saturating_add(deviation_amount);
-                
-                msg!("PRICE DETAILS - Expected token amount: {}, Proposed: {}, Range: [{}, {}]", 
-                     expected_token_amount, amount, min_acceptable_amount, max_acceptable_amount);
-                
-                if amount >= min_acceptable_amount && amount <= max_acceptable_amount {
-                    msg!("Valid amount, allowing token transfer");
-                    Ok(())
-                } else {
-                    msg!("Invalid amount ({}), rejecting token transfer", amount);
-                    return Err(PriceValidationError::PriceOutOfRange.into());
+                check_price_feeds(&state, token_usd_price_feed_account, product_price_feed_accounts)?;
+
+                // Usage is tracked per source owner, whoever signed the transfer
+                let source = InterfaceAccount::<TokenAccount>::try_from(&accounts[0])?;
+                let mint_key = accounts[1].key();
+                let (limits_pubkey, _) =
+                    Pubkey::find_program_address(&[b"limits", mint_key.as_ref()], program_id);
+                let (usage_pubkey, _) = Pubkey::find_program_address(
+                    &[b"usage", mint_key.as_ref(), source.owner.as_ref()],
+                    program_id,
+                );
+                if limits_account.key() != limits_pubkey || usage_account.key() != usage_pubkey {
+                    msg!("Invalid limits or usage account");
+                    return Err(PriceValidationError::InvalidInstruction.into());
                 }
+                enforce_transfer_limits(limits_account, usage_account, amount)?;
+
+                validate_transfer_amount(
+                    &state,
+                    &accounts[1],
+                    &merchant_price_feeds(&merchant, product_price_feed_accounts)?,
+                    token_usd_price_feed_account,
+                    amount,
+                )
             }
             _ => {
                 msg!("Instruction not supported");
-                return Err(PriceValidationError::InvalidInstruction.into());
+                Err(PriceValidationError::InvalidInstruction.into())
             }
         }
     }
 }
 
 
+/// Only the mint's transfer-hook authority may manage its extra account meta
+/// list, and only for mints whose hook points at this program.
+fn check_transfer_hook_authority(program_id: &Pubkey, mint: &AccountInfo, authority: &Pubkey) -> Result<()> {
+    if mint.owner != &anchor_spl::token_2022::ID {
+        return Err(PriceValidationError::InvalidHookProgram.into());
+    }
+
+    let hook = token_interface::get_mint_extension_data::<TransferHookExtension>(mint)?;
+    if Option::<Pubkey>::from(hook.program_id) != Some(*program_id) {
+        msg!("Mint {} does not use this program as its transfer hook", mint.key);
+        return Err(PriceValidationError::InvalidHookProgram.into());
+    }
+    if Option::<Pubkey>::from(hook.authority) != Some(*authority) {
+        msg!("Signer {} is not the transfer hook authority of mint {}", authority, mint.key);
+        return Err(PriceValidationError::UnauthorizedHookAuthority.into());
+    }
+    Ok(())
+}
+
+/// Reads a Switchboard feed and truncates its result to a whole number.
+fn read_feed_price(feed: &AccountInfo) -> Result<u64> {
+    let data = feed.try_borrow_data()?;
+    let feed_data = match AggregatorAccountData::new_from_bytes(&data) {
+        Ok(data) => data,
+        Err(_) => {
+            msg!("Failed to read aggregator data from {}", feed.key);
+            return Err(PriceValidationError::SwitchboardError.into());
+        }
+    };
+
+    let result = match feed_data.get_result() {
+        Ok(result) => result,
+        Err(_) => {
+            msg!("Failed to get result from aggregator {}", feed.key);
+            return Err(PriceValidationError::SwitchboardError.into());
+        }
+    };
+
+    let price = result.mantissa / 10i128.pow(result.scale);
+    match u64::try_from(price) {
+        Ok(price) => Ok(price),
+        Err(_) => {
+            msg!("Failed to convert price from {} to u64", feed.key);
+            Err(PriceValidationError::SwitchboardError.into())
+        }
+    }
+}
+
+/// Checks that a transfer of `amount` pays the price of one of the merchant's
+/// products, `product_price_feeds`, within the configured tolerance.
+///
+/// The price is quoted in UI amounts. For mints with an `InterestBearingConfig`
+/// the UI value of a raw amount grows over time, so the expected UI amount is
+/// converted back to the raw amount that is worth it today.
+///
+/// `amount` is what the sender sends; with a `TransferFeeConfig` on the mint
+/// the recipient gets less. When the state asks for the price to be covered by
+/// the net amount, the expected amount is grossed up by the current epoch's fee.
+fn validate_transfer_amount(
+    state: &PriceValidationState,
+    mint: &AccountInfo,
+    product_price_feeds: &[&AccountInfo],
+    token_usd_price_feed: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    if !state.is_price_validation_active {
+        msg!("Price validation is not active, allowing transfer");
+        return Ok(());
+    }
+
+    let token_usd_price = read_feed_price(token_usd_price_feed)?;
+    msg!("Token/USD price from Switchboard: {}", token_usd_price);
+    if token_usd_price == 0 {
+        return Err(PriceValidationError::SwitchboardError.into());
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let clock = Clock::get()?;
+    for product_price_feed in product_price_feeds {
+        let product_price = read_feed_price(product_price_feed)?;
+        msg!("Product price from Switchboard feed {}: {}", product_price_feed.key, product_price);
+
+        let expected_token_amount =
+            expected_token_amount(state, &mint_data, product_price, token_usd_price, &clock)?;
+        let deviation_amount = expected_token_amount
+            .checked_mul(state.tolerance_basis_points)
+            .ok_or(PriceValidationError::MathOverflow)?
+            / 10000;
+        let min_acceptable_amount = expected_token_amount.saturating_sub(deviation_amount);
+        let max_acceptable_amount = expected_token_amount.saturating_add(deviation_amount);
+
+        msg!("PRICE DETAILS - Expected token amount: {}, Proposed: {}, Range: [{}, {}]",
+             expected_token_amount, amount, min_acceptable_amount, max_acceptable_amount);
+
+        if amount >= min_acceptable_amount && amount <= max_acceptable_amount {
+            msg!("Valid amount ({}), allowing token transfer", amount);
+            return Ok(());
+        }
+    }
+
+    msg!("Invalid amount ({}), rejecting token transfer", amount);
+    Err(PriceValidationError::PriceOutOfRange.into())
+}
+
+/// Raw amount of the mint that pays `product_price` at `token_usd_price`.
+///
+/// Extension types this version of Token-2022 does not know, such as
+/// `ScaledUiAmount`, may rescale UI amounts, so such mints are rejected
+/// rather than priced as if their UI and raw amounts were equal.
+fn expected_token_amount(
+    state: &PriceValidationState,
+    mint_data: &[u8],
+    product_price: u64,
+    token_usd_price: u64,
+    clock: &Clock,
+) -> Result<u64> {
+    let mut expected_token_amount = product_price
+        .checked_mul(1_000_000_000)
+        .ok_or(PriceValidationError::MathOverflow)?
+        / token_usd_price;
+
+    let mint_state = StateWithExtensions::<MintState>::unpack(mint_data)?;
+    let extension_types = match mint_state.get_extension_types() {
+        Ok(extension_types) => extension_types,
+        Err(_) => {
+            msg!("Mint has an unsupported extension, such as ScaledUiAmount");
+            return Err(PriceValidationError::UnsupportedMintExtension.into());
+        }
+    };
+
+    if extension_types.contains(&ExtensionType::InterestBearingConfig) {
+        let interest_config = mint_state.get_extension::<InterestBearingConfig>()?;
+        let ui_amount = expected_token_amount;
+        expected_token_amount = raw_amount_for_ui_amount(interest_config, ui_amount, clock.unix_timestamp)?;
+        msg!("Interest-bearing mint: UI amount {} is worth {} raw", ui_amount, expected_token_amount);
+    }
+
+    if state.price_covered_by_net_amount && extension_types.contains(&ExtensionType::TransferFeeConfig) {
+        let fee_config = mint_state.get_extension::<TransferFeeConfig>()?;
+        let fee = fee_config
+            .calculate_inverse_epoch_fee(clock.epoch, expected_token_amount)
+            .ok_or(PriceValidationError::MathOverflow)?;
+        msg!("Transfer fee of {} added to the expected amount", fee);
+        expected_token_amount = expected_token_amount
+            .checked_add(fee)
+            .ok_or(PriceValidationError::MathOverflow)?;
+    }
+
+    Ok(expected_token_amount)
+}
+
+/// Raw amount whose interest-accrued UI value equals `ui_amount`, both in base
+/// units. Passing zero decimals to the extension keeps the conversion in base
+/// units instead of whole tokens.
+fn raw_amount_for_ui_amount(
+    config: &InterestBearingConfig,
+    ui_amount: u64,
+    unix_timestamp: i64,
+) -> Result<u64> {
+    config
+        .try_ui_amount_into_amount(&ui_amount.to_string(), 0, unix_timestamp)
+        .map_err(|_| PriceValidationError::MathOverflow.into())
+}
+
+/// Only transfers into a registered merchant's payout account are purchases;
+/// transfers to other owners, such as gifts between wallets, skip price
+/// validation. Any other token account of a merchant owner is rejected so
+/// payments cannot be routed around validation and limits.
+fn merchant_payment(merchant: &AccountInfo, destination: &Pubkey) -> Result<Option<Merchant>> {
+    if merchant.owner != &crate::ID || merchant.data_is_empty() {
+        msg!("Destination is not a registered merchant, skipping price validation");
+        return Ok(None);
+    }
+
+    let data = merchant.try_borrow_data()?;
+    let merchant = Merchant::try_deserialize(&mut &data[..])?;
+    if merchant.payout_token_account != *destination {
+        msg!("Merchant {} is paid into {}, not {}",
+             merchant.owner, merchant.payout_token_account, destination);
+        return Err(PriceValidationError::MerchantPayoutAccountMismatch.into());
+    }
+    if merchant.is_suspended {
+        msg!("Merchant {} is suspended", merchant.owner);
+        return Err(PriceValidationError::MerchantSuspended.into());
+    }
+    Ok(Some(merchant))
+}
+
+/// Feeds of the merchant's products among the configured product feeds
+/// passed to the hook.
+fn merchant_price_feeds<'a, 'info>(
+    merchant: &Merchant,
+    product_price_feeds: &'a [AccountInfo<'info>],
+) -> Result<Vec<&'a AccountInfo<'info>>> {
+    let feeds: Vec<_> = product_price_feeds
+        .iter()
+        .filter(|feed| merchant.product_price_feeds.contains(feed.key))
+        .collect();
+    if feeds.is_empty() {
+        msg!("Merchant {} sells no product priced by the configured feeds", merchant.owner);
+        return Err(PriceValidationError::ProductNotListed.into());
+    }
+    Ok(feeds)
+}
+
+/// Merchant payments must be priced by the configured feeds: the token/USD
+/// feed, then the product feeds in order after the fixed hook accounts.
+/// Other transfers never read the feeds, so a list that has not yet been
+/// rewritten after a feed change only blocks payments.
+fn check_price_feeds(
+    state: &PriceValidationState,
+    token_usd_price_feed: &AccountInfo,
+    product_price_feeds: &[AccountInfo],
+) -> Result<()> {
+    if token_usd_price_feed.key != &state.token_usd_price_feed_pubkey {
+        msg!("Token/USD price feed does not match the configured feed");
+        return Err(PriceValidationError::InvalidPriceFeed.into());
+    }
+    if product_price_feeds.len() != state.product_price_feeds.len()
+        || product_price_feeds.iter().zip(&state.product_price_feeds).any(|(feed, key)| feed.key != key)
+    {
+        msg!("Product price feeds do not match the configured feeds");
+        return Err(PriceValidationError::InvalidPriceFeed.into());
+    }
+    Ok(())
+}
+
+fn check_product_price_feeds_config(product_price_feeds: &[Pubkey]) -> Result<()> {
+    if product_price_feeds.is_empty() || product_price_feeds.len() > MAX_PRODUCT_FEEDS {
+        return Err(PriceValidationError::InvalidProductFeeds.into());
+    }
+    Ok(())
+}
+
+/// Applies the mint's limits, if it has any, to a purchase and records it in
+/// the source owner's rolling 24h volume. Only called for merchant payments.
+fn enforce_transfer_limits(limits: &AccountInfo, usage: &AccountInfo, amount: u64) -> Result<()> {
+    if limits.owner != &crate::ID || limits.data_is_empty() {
+        return Ok(());
+    }
+    let limits = MintLimits::try_deserialize(&mut &limits.try_borrow_data()?[..])?;
+    limits.check_transfer_amount(amount)?;
+
+    if limits.max_daily_volume == 0 {
+        return Ok(());
+    }
+    if usage.owner != &crate::ID || usage.data_is_empty() {
+        msg!("Usage account {} must be initialized before buying", usage.key);
+        return Err(PriceValidationError::UsageAccountMissing.into());
+    }
+
+    let mut usage_data = usage.try_borrow_mut_data()?;
+    let mut owner_usage = OwnerUsage::try_deserialize(&mut &usage_data[..])?;
+    let hour = Clock::get()?.unix_timestamp / SECONDS_PER_HOUR;
+    let volume = owner_usage.record_purchase(hour, amount, limits.max_daily_volume)?;
+    owner_usage.try_serialize(&mut &mut usage_data[..])?;
+
+    msg!("24h volume for {}: {}", owner_usage.owner, volume);
+    Ok(())
+}
+
+/// Rejects direct calls: both token accounts must belong to the mint, and
+/// Token-2022 flags the source as transferring while it invokes the hook.
+fn check_is_transferring<'a>(source: &AccountInfo<'a>, mint: &AccountInfo, destination: &AccountInfo<'a>) -> Result<()> {
+    for account in [source, destination] {
+        if account.owner != &anchor_spl::token_2022::ID {
+            return Err(PriceValidationError::NotTransferring.into());
+        }
+        let data = account.try_borrow_data()?;
+        let token_account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+        if token_account.base.mint != *mint.key {
+            return Err(PriceValidationError::NotTransferring.into());
+        }
+    }
+
+    let data = source.try_borrow_data()?;
+    let source_account = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    let hook_account = source_account.get_extension::<TransferHookAccount>()?;
+    if !bool::from(hook_account.transferring) {
+        msg!("Transfer hook called outside of a token transfer");
+        return Err(PriceValidationError::NotTransferring.into());
+    }
+    Ok(())
+}
+
+/// Byte offset of the owner in a token account.
+const TOKEN_ACCOUNT_OWNER_OFFSET: u8 = 32;
+
+pub const MAX_MERCHANT_PRODUCTS: usize = 8;
+
+/// Product feeds the state can configure; every mint's list carries all of them.
+pub const MAX_PRODUCT_FEEDS: usize = 8;
+
+/// Hourly buckets making up the rolling volume window.
+pub const USAGE_WINDOW_HOURS: usize = 24;
+
+pub const SECONDS_PER_HOUR: i64 = 60 * 60;
+
+/// Accounts the hook needs after source, mint, destination, owner and the
+/// list itself: the state PDA, the token/USD feed, the destination owner's
+/// merchant PDA, the mint's limits, the source owner's usage and then every
+/// configured product feed, since which of them prices a transfer depends on
+/// the merchant.
+/// Feeds are stored as fixed pubkeys, so the list must be rewritten whenever
+/// they change.
+fn price_extra_account_metas(state: &PriceValidationState) -> Result<Vec<ExtraAccountMeta>> {
+    let mut metas = vec![
+        ExtraAccountMeta::new_with_seeds(
+            &[Seed::Literal { bytes: b"state_v3".to_vec() }],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_pubkey(&state.token_usd_price_feed_pubkey, false, false)?,
+        // Merchant PDA of the destination owner; it only exists for registered merchants
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"merchant".to_vec() },
+                Seed::AccountData { account_index: 2, data_index: TOKEN_ACCOUNT_OWNER_OFFSET, length: 32 },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"limits".to_vec() },
+                Seed::AccountKey { index: 1 },
+            ],
+            false,
+            false,
+        )?,
+        // Usage of the source owner rather than the signer, so delegates share its limits
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: b"usage".to_vec() },
+                Seed::AccountKey { index: 1 },
+                Seed::AccountData { account_index: 0, data_index: TOKEN_ACCOUNT_OWNER_OFFSET, length: 32 },
+            ],
+            false,
+            true,
+        )?,
+    ];
+    for feed in &state.product_price_feeds {
+        metas.push(ExtraAccountMeta::new_with_pubkey(feed, false, false)?);
+    }
+    Ok(metas)
+}
+
+fn write_extra_account_metas(
+    program_id: &Pubkey,
+    mint: &AccountInfo,
+    extra_account_metas: &AccountInfo,
+    metas: &[ExtraAccountMeta],
+) -> Result<()> {
+    let (expected_list, _) =
+        Pubkey::find_program_address(&[b"extra-account-metas", mint.key.as_ref()], program_id);
+    if extra_account_metas.key() != expected_list || extra_account_metas.owner != program_id {
+        msg!("Extra account meta list {} does not belong to mint {}", extra_account_metas.key, mint.key);
+        return Err(PriceValidationError::InvalidExtraAccountMetaList.into());
+    }
+
+    let mut data = extra_account_metas.try_borrow_mut_data()?;
+    data.fill(0);
+    ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, metas)?;
+    Ok(())
+}
+
+/// Creates the PDA signed for by `signer_seeds` as a rent-exempt account of
+/// the program. `create_account` fails once anyone has sent lamports to the
+/// address, so a funded PDA is topped up from `payer` and then allocated and
+/// assigned instead.
+fn create_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+    signer_seeds: &[&[u8]],
+    size: usize,
+) -> Result<()> {
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(size);
+    let lamports = account.lamports();
+    if lamports == 0 {
+        msg!("Creating account with {} lamports for rent exemption", rent_exempt_lamports);
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_exempt_lamports,
+                size as u64,
+                program_id,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        return Ok(());
+    }
+
+    msg!("Account {} already holds {} lamports, allocating it in place", account.key, lamports);
+    if rent_exempt_lamports > lamports {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_exempt_lamports - lamports,
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, size as u64),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    Ok(())
+}
+
+/// Resizes an existing account of the program to `size` bytes, topping up
+/// rent from `payer` when it grows and returning the excess when it shrinks.
+fn resize_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    size: usize,
+) -> Result<()> {
+    let current_size = account.data_len();
+    if current_size == size {
+        return Ok(());
+    }
+
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(size);
+    let lamports = account.lamports();
+    if rent_exempt_lamports > lamports {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_exempt_lamports - lamports,
+        )?;
+    } else if lamports > rent_exempt_lamports {
+        **account.try_borrow_mut_lamports()? -= lamports - rent_exempt_lamports;
+        **payer.try_borrow_mut_lamports()? += lamports - rent_exempt_lamports;
+    }
+
+    account.realloc(size, false)?;
+    msg!("Account {} resized from {} to {} bytes", account.key, current_size, size);
+    Ok(())
+}
+
+/// Layout version written by this program; the layouts before it carried no
+/// version and are told apart by size in `read_legacy_state`.
+pub const STATE_VERSION: u8 = 4;
+
+pub const STATE_SIZE: usize = 8 + 4 + 32 * MAX_PRODUCT_FEEDS + 32 + 8 + 1 + 32 + 1 + 1;
+
+/// Unversioned layouts, all holding a single product feed, the token/USD
+/// feed, the tolerance and the active flag: the first without anything else,
+/// the second with the authority and the third also with
+/// `price_covered_by_net_amount`.
+const LEGACY_STATE_SIZES: [usize; 3] = [
+    8 + 32 + 32 + 8 + 1,
+    8 + 32 + 32 + 8 + 1 + 32,
+    8 + 32 + 32 + 8 + 1 + 32 + 1,
+];
+
+/// Reads a state account in one of the legacy layouts as the current one.
+fn read_legacy_state(data: &[u8], default_authority: Pubkey) -> Result<PriceValidationState> {
+    if !LEGACY_STATE_SIZES.contains(&data.len()) || data[..8] != PriceValidationState::DISCRIMINATOR {
+        msg!("State of {} bytes is not in a legacy layout", data.len());
+        return Err(PriceValidationError::UnsupportedStateLayout.into());
+    }
+
+    let mut fields = &data[8..];
+    let product_price_feed = Pubkey::deserialize(&mut fields)?;
+    let token_usd_price_feed_pubkey = Pubkey::deserialize(&mut fields)?;
+    let tolerance_basis_points = u64::deserialize(&mut fields)?;
+    let is_price_validation_active = bool::deserialize(&mut fields)?;
+    let authority = if fields.is_empty() { default_authority } else { Pubkey::deserialize(&mut fields)? };
+    let price_covered_by_net_amount = if fields.is_empty() { false } else { bool::deserialize(&mut fields)? };
+
+    Ok(PriceValidationState {
+        product_price_feeds: vec![product_price_feed],
+        token_usd_price_feed_pubkey,
+        tolerance_basis_points,
+        is_price_validation_active,
+        authority,
+        price_covered_by_net_amount,
+        version: STATE_VERSION,
+    })
+}
+
 #[account]
 pub struct PriceValidationState {
-    pub product_price_feed_pubkey: Pubkey,
+    /// Switchboard feeds of the products merchants may be paid for
+    pub product_price_feeds: Vec<Pubkey>,
     pub token_usd_price_feed_pubkey: Pubkey,
     pub tolerance_basis_points: u64,
     pub is_price_validation_active: bool,
+    /// Key allowed to update the configuration
+    pub authority: Pubkey,
+    /// Whether the product price must be covered by what the recipient gets
+    /// after transfer fees, rather than by the amount sent
+    pub price_covered_by_net_amount: bool,
+    pub version: u8,
+}
+
+/// Merchant keyed by the owner of the token accounts it is paid into.
+#[account]
+pub struct Merchant {
+    pub owner: Pubkey,
+    /// Token account purchases must be paid into
+    pub payout_token_account: Pubkey,
+    /// Switchboard feeds of the products the merchant sells
+    pub product_price_feeds: Vec<Pubkey>,
+    pub is_suspended: bool,
+    pub bump: u8,
+}
+
+/// Optional purchase limits for a mint; zero disables a limit.
+#[account]
+pub struct MintLimits {
+    pub mint: Pubkey,
+    pub max_transfer_amount: u64,
+    /// Maximum volume per source owner over a rolling 24 hours
+    pub max_daily_volume: u64,
+    pub bump: u8,
+}
+
+impl MintLimits {
+    pub fn check_transfer_amount(&self, amount: u64) -> Result<()> {
+        if self.max_transfer_amount > 0 && amount > self.max_transfer_amount {
+            msg!("Transfer of {} exceeds the limit of {}", amount, self.max_transfer_amount);
+            return Err(PriceValidationError::TransferAmountLimitExceeded.into());
+        }
+        Ok(())
+    }
+}
+
+/// Purchase volume of one owner for one mint, in hourly buckets.
+#[account]
+pub struct OwnerUsage {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub hourly_volume: [u64; USAGE_WINDOW_HOURS],
+    /// Hour, counted from the Unix epoch, of the most recent bucket
+    pub last_hour: i64,
+    pub bump: u8,
+}
+
+impl OwnerUsage {
+    /// Clears the buckets of hours that have left the window since the last purchase.
+    pub fn roll_to(&mut self, hour: i64) {
+        let elapsed = hour.saturating_sub(self.last_hour);
+        if elapsed >= USAGE_WINDOW_HOURS as i64 {
+            self.hourly_volume = [0; USAGE_WINDOW_HOURS];
+        } else {
+            for past_hour in (self.last_hour + 1)..=hour {
+                self.hourly_volume[past_hour.rem_euclid(USAGE_WINDOW_HOURS as i64) as usize] = 0;
+            }
+        }
+        self.last_hour = self.last_hour.max(hour);
+    }
+
+    pub fn rolling_volume(&self) -> u64 {
+        self.hourly_volume.iter().fold(0u64, |total, volume| total.saturating_add(*volume))
+    }
+
+    /// Adds a purchase made during `hour` to the window if the 24h volume
+    /// stays within `max_daily_volume`, and returns the new volume.
+    pub fn record_purchase(&mut self, hour: i64, amount: u64, max_daily_volume: u64) -> Result<u64> {
+        self.roll_to(hour);
+
+        let volume = self
+            .rolling_volume()
+            .checked_add(amount)
+            .ok_or(PriceValidationError::MathOverflow)?;
+        if volume > max_daily_volume {
+            msg!("24h volume of {} would exceed the limit of {}", volume, max_daily_volume);
+            return Err(PriceValidationError::DailyVolumeLimitExceeded.into());
+        }
+
+        let bucket = &mut self.hourly_volume[hour.rem_euclid(USAGE_WINDOW_HOURS as i64) as usize];
+        *bucket = bucket.checked_add(amount).ok_or(PriceValidationError::MathOverflow)?;
+        Ok(volume)
+    }
 }
 
 #[derive(Accounts)]
 pub struct TransferHook<'info> {
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub source: AccountInfo<'info>,
-    /// CHECK: Passed from token program via CPI, no additional checks needed
+    /// Checked to be a transferring Token-2022 account of the mint
+    pub source: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Passed from token program via CPI
     pub mint: AccountInfo<'info>,
-    /// CHECK: Passed from token program via CPI, no additional checks needed
-    pub destination: AccountInfo<'info>,
+    /// Checked to be a Token-2022 account of the mint
+    pub destination: InterfaceAccount<'info, TokenAccount>,
     /// CHECK: Passed from token program via CPI, no additional checks needed
     pub owner: AccountInfo<'info>,
     /// CHECK: Extra account meta list
@@ -376,15 +1022,112 @@ pub struct TransferHook<'info> {
     /// State account containing configuration
     #[account(seeds = [b"state_v3"], bump)]
     pub state: Account<'info, PriceValidationState>,
-    /// CHECK: Switchboard product price feed account
-    pub product_price_feed: AccountInfo<'info>,
-    /// CHECK: Switchboard token/USD price feed account
+    /// CHECK: Switchboard token/USD price feed account, checked against the state for merchant payments
     pub token_usd_price_feed: AccountInfo<'info>,
+    /// CHECK: Merchant PDA of the destination owner, empty unless it is a registered merchant
+    #[account(seeds = [b"merchant", destination.owner.as_ref()], bump)]
+    pub merchant: AccountInfo<'info>,
+    /// CHECK: Limits PDA of the mint, empty if the mint has no limits
+    #[account(seeds = [b"limits", mint.key().as_ref()], bump)]
+    pub limits: AccountInfo<'info>,
+    /// CHECK: Usage PDA of the source owner, read and written only if the mint caps volume
+    #[account(mut, seeds = [b"usage", mint.key().as_ref(), source.owner.as_ref()], bump)]
+    pub usage: AccountInfo<'info>,
+    // The configured product feeds follow as remaining accounts
+}
+
+#[derive(Accounts)]
+pub struct SetMintLimits<'info> {
+    #[account(
+        seeds = [b"state_v3"],
+        bump,
+        has_one = authority @ PriceValidationError::Unauthorized
+    )]
+    pub state: Account<'info, PriceValidationState>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 32 + 8 + 8 + 1,
+        seeds = [b"limits", mint.key().as_ref()],
+        bump
+    )]
+    pub limits: Account<'info, MintLimits>,
+    /// CHECK: Token mint the limits apply to
+    pub mint: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUsage<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + 32 + 32 + 8 * USAGE_WINDOW_HOURS + 8 + 1,
+        seeds = [b"usage", mint.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub usage: Account<'info, OwnerUsage>,
+    /// CHECK: Token mint the usage is tracked for
+    pub mint: AccountInfo<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(merchant_owner: Pubkey)]
+pub struct OnboardMerchant<'info> {
+    #[account(
+        seeds = [b"state_v3"],
+        bump,
+        has_one = authority @ PriceValidationError::Unauthorized
+    )]
+    pub state: Account<'info, PriceValidationState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 32 + 4 + 32 * MAX_MERCHANT_PRODUCTS + 1 + 1,
+        seeds = [b"merchant", merchant_owner.as_ref()],
+        bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+    #[account(
+        constraint = payout_token_account.owner == merchant_owner @ PriceValidationError::InvalidMerchantConfig
+    )]
+    pub payout_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(merchant_owner: Pubkey)]
+pub struct SuspendMerchant<'info> {
+    #[account(
+        seeds = [b"state_v3"],
+        bump,
+        has_one = authority @ PriceValidationError::Unauthorized
+    )]
+    pub state: Account<'info, PriceValidationState>,
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant_owner.as_ref()],
+        bump = merchant.bump
+    )]
+    pub merchant: Account<'info, Merchant>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct UpdateConfig<'info> {
-    #[account(mut, seeds = [b"state_v3"], bump)]
+    #[account(
+        mut,
+        seeds = [b"state_v3"],
+        bump,
+        has_one = authority @ PriceValidationError::Unauthorized
+    )]
     pub state: Account<'info, PriceValidationState>,
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -393,9 +1136,39 @@ pub struct UpdateConfig<'info> {
 #[derive(Accounts)]
 pub struct InitializeExtraAccountMetaList<'info> {
     /// CHECK: This account will be created in the initialize_extra_account_meta_list function
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_metas: AccountInfo<'info>,
+    /// CHECK: Token mint used to create extra account meta list, checked
+    /// against its transfer hook extension
+    pub mint: AccountInfo<'info>,
+    #[account(seeds = [b"state_v3"], bump)]
+    pub state: Account<'info, PriceValidationState>,
+    /// Transfer hook authority of the mint
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    /// CHECK: Existing validation account of the mint
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
     pub extra_account_metas: AccountInfo<'info>,
-    /// CHECK: Token mint used to create extra account meta list
+    /// CHECK: Token mint the list belongs to, checked against its transfer hook extension
     pub mint: AccountInfo<'info>,
+    #[account(seeds = [b"state_v3"], bump)]
+    pub state: Account<'info, PriceValidationState>,
+    /// Transfer hook authority of the mint, pays for extra space and gets
+    /// back rent freed by a smaller list
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -406,20 +1179,391 @@ pub struct InitializeState<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + 32 + 32 + 8 + 1, 
+        space = STATE_SIZE,
         seeds = [b"state_v3"],
         bump
     )]
     pub state: Account<'info, PriceValidationState>,
-    /// CHECK: Switchboard product price feed account
-    pub product_price_feed: AccountInfo<'info>,
     /// CHECK: Switchboard token/USD price feed account
     pub token_usd_price_feed: AccountInfo<'info>,
+    /// Program upgrade authority, becomes the state authority
     #[account(mut)]
     pub payer: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PriceValidationTransferHook>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(payer.key())
+            @ PriceValidationError::UnauthorizedInitializer
+    )]
+    pub program_data: Account<'info, ProgramData>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// CHECK: State in a legacy layout, which cannot be deserialized as the current one
+    #[account(mut, seeds = [b"state_v3"], bump, owner = crate::ID)]
+    pub state: AccountInfo<'info>,
+    /// Program upgrade authority, pays for the larger account
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::PriceValidationTransferHook>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ PriceValidationError::UnauthorizedInitializer
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token_2022::spl_token_2022::extension::{
+        transfer_fee::TransferFee, BaseStateWithExtensionsMut, StateWithExtensionsMut,
+    };
+    use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
+
+    const SECONDS_PER_YEAR: i64 = 31_556_736;
+
+    fn interest_config(rate_bps: i16, initialized_at: i64) -> InterestBearingConfig {
+        InterestBearingConfig {
+            rate_authority: OptionalNonZeroPubkey::default(),
+            initialization_timestamp: initialized_at.into(),
+            pre_update_average_rate: rate_bps.into(),
+            last_update_timestamp: initialized_at.into(),
+            current_rate: rate_bps.into(),
+        }
+    }
+
+    #[test]
+    fn no_accrued_interest_keeps_amount() {
+        let config = interest_config(500, 1_000);
+        assert_eq!(raw_amount_for_ui_amount(&config, 1_000_000_000, 1_000).unwrap(), 1_000_000_000);
+    }
+
+    #[test]
+    fn accrued_interest_lowers_raw_amount() {
+        // A year at 5% continuously compounded scales UI amounts by e^0.05
+        let config = interest_config(500, 0);
+        let raw = raw_amount_for_ui_amount(&config, 1_051_271_096, SECONDS_PER_YEAR).unwrap();
+        assert!(raw.abs_diff(1_000_000_000) <= 1, "raw amount {}", raw);
+    }
+
+    #[test]
+    fn negative_rate_raises_raw_amount() {
+        let config = interest_config(-500, 0);
+        let raw = raw_amount_for_ui_amount(&config, 951_229_425, SECONDS_PER_YEAR).unwrap();
+        assert!(raw.abs_diff(1_000_000_000) <= 1, "raw amount {}", raw);
+    }
+
+    fn test_state() -> PriceValidationState {
+        PriceValidationState {
+            product_price_feeds: vec![Pubkey::new_unique()],
+            token_usd_price_feed_pubkey: Pubkey::new_unique(),
+            tolerance_basis_points: 0,
+            is_price_validation_active: true,
+            authority: Pubkey::new_unique(),
+            price_covered_by_net_amount: false,
+            version: STATE_VERSION,
+        }
+    }
+
+    fn interest_bearing_mint(config: InterestBearingConfig) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<MintState>(&[ExtensionType::InterestBearingConfig])
+            .unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        *mint.init_extension::<InterestBearingConfig>(true).unwrap() = config;
+        mint.base = MintState { decimals: 9, is_initialized: true, ..MintState::default() };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn interest_bearing_mint_converts_expected_amount() {
+        // Product at 1.051271096 tokens after a year at 5%, i.e. 1 token's worth of raw amount
+        let mint = interest_bearing_mint(interest_config(500, 0));
+        let clock = Clock { unix_timestamp: SECONDS_PER_YEAR, ..Clock::default() };
+        let expected = expected_token_amount(&test_state(), &mint, 1_051_271_096, 1_000_000_000, &clock).unwrap();
+        assert!(expected.abs_diff(1_000_000_000) <= 1, "expected amount {}", expected);
+
+        let clock = Clock { unix_timestamp: 0, ..Clock::default() };
+        let expected = expected_token_amount(&test_state(), &mint, 1_051_271_096, 1_000_000_000, &clock).unwrap();
+        assert_eq!(expected, 1_051_271_096);
+    }
+
+    fn transfer_fee_mint(transfer_fee_basis_points: u16, maximum_fee: u64) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<MintState>(&[ExtensionType::TransferFeeConfig])
+            .unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: transfer_fee_basis_points.into(),
+        };
+        let config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = fee;
+        config.newer_transfer_fee = fee;
+        mint.base = MintState { decimals: 9, is_initialized: true, ..MintState::default() };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    /// Expected amount for a product worth one token, and what the recipient
+    /// keeps of it after the transfer fee.
+    fn amounts_after_fee(mint: &[u8], price_covered_by_net_amount: bool) -> (u64, u64) {
+        let state = PriceValidationState { price_covered_by_net_amount, ..test_state() };
+        let clock = Clock::default();
+        let expected = expected_token_amount(&state, mint, 1_000_000_000, 1_000_000_000, &clock).unwrap();
+        let mint_state = StateWithExtensions::<MintState>::unpack(mint).unwrap();
+        let fee = mint_state
+            .get_extension::<TransferFeeConfig>()
+            .unwrap()
+            .calculate_epoch_fee(clock.epoch, expected)
+            .unwrap();
+        (expected, expected - fee)
+    }
+
+    #[test]
+    fn transfer_fees_are_added_only_when_the_net_amount_must_cover_the_price() {
+        // 1% fee with a cap well above it
+        let mint = transfer_fee_mint(100, 1_000_000_000);
+
+        let (expected, _) = amounts_after_fee(&mint, false);
+        assert_eq!(expected, 1_000_000_000);
+
+        let (expected, net) = amounts_after_fee(&mint, true);
+        assert_eq!(expected, 1_010_101_011);
+        assert_eq!(net, 1_000_000_000);
+    }
 
+    #[test]
+    fn transfer_fees_at_the_maximum_fee_gross_up_by_the_cap() {
+        // 1% would be about 10_101_011, so the 5_000_000 cap applies
+        let mint = transfer_fee_mint(100, 5_000_000);
 
+        let (expected, _) = amounts_after_fee(&mint, false);
+        assert_eq!(expected, 1_000_000_000);
 
+        let (expected, net) = amounts_after_fee(&mint, true);
+        assert_eq!(expected, 1_005_000_000);
+        assert_eq!(net, 1_000_000_000);
+
+        // Exactly at the cap, the 1% fee and the cap agree
+        let mint = transfer_fee_mint(100, 10_101_011);
+        let (expected, net) = amounts_after_fee(&mint, true);
+        assert_eq!(expected, 1_010_101_011);
+        assert_eq!(net, 1_000_000_000);
+    }
+
+    #[test]
+    fn unknown_ui_amount_extensions_are_rejected() {
+        // Relabel the extension as ScaledUiAmount, which this Token-2022 version does not know
+        const SCALED_UI_AMOUNT_EXTENSION: u16 = 25;
+        let mut mint = interest_bearing_mint(interest_config(500, 0));
+        let type_start = TokenAccountState::LEN + 1;
+        mint[type_start..type_start + 2].copy_from_slice(&SCALED_UI_AMOUNT_EXTENSION.to_le_bytes());
+
+        let err = expected_token_amount(&test_state(), &mint, 100, 100, &Clock::default()).unwrap_err();
+        assert_eq!(err, PriceValidationError::UnsupportedMintExtension.into());
+    }
+
+    #[test]
+    fn legacy_states_migrate_to_the_current_layout() {
+        let (product_feed, token_usd_feed, authority, signer) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = PriceValidationState::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(product_feed.as_ref());
+        data.extend_from_slice(token_usd_feed.as_ref());
+        data.extend_from_slice(&2_000u64.to_le_bytes());
+        data.push(1);
+
+        let state = read_legacy_state(&data, signer).unwrap();
+        assert_eq!(state.product_price_feeds, vec![product_feed]);
+        assert_eq!(state.token_usd_price_feed_pubkey, token_usd_feed);
+        assert_eq!(state.tolerance_basis_points, 2_000);
+        assert!(state.is_price_validation_active);
+        assert_eq!(state.authority, signer);
+        assert!(!state.price_covered_by_net_amount);
+
+        data.extend_from_slice(authority.as_ref());
+        assert_eq!(read_legacy_state(&data, signer).unwrap().authority, authority);
+
+        data.push(1);
+        let state = read_legacy_state(&data, signer).unwrap();
+        assert!(state.price_covered_by_net_amount);
+        assert_eq!(state.version, STATE_VERSION);
+
+        let mut current = Vec::new();
+        state.try_serialize(&mut current).unwrap();
+        current.resize(STATE_SIZE, 0);
+        assert!(read_legacy_state(&current, signer).is_err());
+    }
+
+    fn merchant_data(merchant: &Merchant) -> Vec<u8> {
+        let mut data = Vec::new();
+        merchant.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn test_merchant(payout_token_account: Pubkey, is_suspended: bool) -> Merchant {
+        Merchant {
+            owner: Pubkey::new_unique(),
+            payout_token_account,
+            product_price_feeds: vec![Pubkey::new_unique()],
+            is_suspended,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn transfers_to_other_merchant_accounts_are_rejected() {
+        let payout = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = merchant_data(&test_merchant(payout, true));
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+
+        assert!(merchant_payment(&account, &Pubkey::new_unique()).is_err());
+        assert!(merchant_payment(&account, &payout).is_err());
+    }
+
+    #[test]
+    fn merchant_payments_are_priced_by_the_merchant_feeds() {
+        let payout = Pubkey::new_unique();
+        let merchant = test_merchant(payout, false);
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = merchant_data(&merchant);
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+        assert!(merchant_payment(&account, &payout).unwrap().is_some());
+
+        let other_feed = Pubkey::new_unique();
+        let merchant_feed = merchant.product_price_feeds[0];
+        let (mut other_lamports, mut merchant_lamports) = (0, 0);
+        let (mut other_data, mut merchant_feed_data) = (Vec::new(), Vec::new());
+        let feeds = [
+            AccountInfo::new(&other_feed, false, false, &mut other_lamports, &mut other_data, &key, false, 0),
+            AccountInfo::new(&merchant_feed, false, false, &mut merchant_lamports, &mut merchant_feed_data, &key, false, 0),
+        ];
+        let priced_by: Vec<_> = merchant_price_feeds(&merchant, &feeds).unwrap().iter().map(|feed| *feed.key).collect();
+        assert_eq!(priced_by, vec![merchant_feed]);
+        assert!(merchant_price_feeds(&merchant, &feeds[..1]).is_err());
+    }
+
+    #[test]
+    fn merchant_payments_require_the_configured_feeds() {
+        let mut state = test_state();
+        state.product_price_feeds.push(Pubkey::new_unique());
+        let (token_usd, first, second) =
+            (state.token_usd_price_feed_pubkey, state.product_price_feeds[0], state.product_price_feeds[1]);
+        let (stale, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = [0u64; 4];
+        let mut data: [Vec<u8>; 4] = Default::default();
+        let [l0, l1, l2, l3] = &mut lamports;
+        let [d0, d1, d2, d3] = &mut data;
+        let token_usd = AccountInfo::new(&token_usd, false, false, l0, d0, &owner, false, 0);
+        let stale = AccountInfo::new(&stale, false, false, l1, d1, &owner, false, 0);
+        let first = AccountInfo::new(&first, false, false, l2, d2, &owner, false, 0);
+        let second = AccountInfo::new(&second, false, false, l3, d3, &owner, false, 0);
+
+        let configured = [first.clone(), second.clone()];
+        assert!(check_price_feeds(&state, &token_usd, &configured).is_ok());
+        assert!(check_price_feeds(&state, &stale, &configured).is_err());
+        assert!(check_price_feeds(&state, &token_usd, &[second.clone(), first.clone()]).is_err());
+        assert!(check_price_feeds(&state, &token_usd, &configured[..1]).is_err());
+        assert!(check_price_feeds(&state, &token_usd, &[first, stale]).is_err());
+    }
+
+    fn test_usage(last_hour: i64) -> OwnerUsage {
+        OwnerUsage {
+            owner: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            hourly_volume: [0; USAGE_WINDOW_HOURS],
+            last_hour,
+            bump: 255,
+        }
+    }
+
+    const START_HOUR: i64 = 480_000;
+
+    #[test]
+    fn purchases_leave_the_window_after_24_hours() {
+        let mut usage = test_usage(START_HOUR);
+        usage.record_purchase(START_HOUR, 100, 1_000).unwrap();
+
+        usage.roll_to(START_HOUR + 23);
+        assert_eq!(usage.rolling_volume(), 100);
+        usage.roll_to(START_HOUR + 24);
+        assert_eq!(usage.rolling_volume(), 0);
+        assert_eq!(usage.last_hour, START_HOUR + 24);
+    }
+
+    #[test]
+    fn partial_rolls_only_clear_expired_hours() {
+        let mut usage = test_usage(START_HOUR);
+        for hour in 0..4 {
+            usage.record_purchase(START_HOUR + hour, 10 * (hour as u64 + 1), 1_000).unwrap();
+        }
+        assert_eq!(usage.rolling_volume(), 10 + 20 + 30 + 40);
+
+        // Moving on 25 hours from the start drops the first two hours only
+        usage.roll_to(START_HOUR + 25);
+        assert_eq!(usage.rolling_volume(), 30 + 40);
+
+        // An hour earlier than the latest bucket neither clears nor rewinds
+        usage.roll_to(START_HOUR + 20);
+        assert_eq!(usage.rolling_volume(), 30 + 40);
+        assert_eq!(usage.last_hour, START_HOUR + 25);
+    }
+
+    #[test]
+    fn gaps_longer_than_the_window_clear_every_bucket() {
+        let mut usage = test_usage(START_HOUR);
+        for hour in 0..USAGE_WINDOW_HOURS as i64 {
+            usage.record_purchase(START_HOUR + hour, 10, 1_000).unwrap();
+        }
+        assert_eq!(usage.rolling_volume(), 10 * USAGE_WINDOW_HOURS as u64);
+
+        usage.roll_to(START_HOUR + 1_000);
+        assert_eq!(usage.rolling_volume(), 0);
+        assert_eq!(usage.record_purchase(START_HOUR + 1_000, 1_000, 1_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn daily_volume_may_reach_the_cap_but_not_pass_it() {
+        let mut usage = test_usage(START_HOUR);
+        assert_eq!(usage.record_purchase(START_HOUR, 600, 1_000).unwrap(), 600);
+        assert_eq!(usage.record_purchase(START_HOUR + 5, 400, 1_000).unwrap(), 1_000);
+        assert!(usage.record_purchase(START_HOUR + 6, 1, 1_000).is_err());
+        // A rejected purchase isn't recorded
+        assert_eq!(usage.rolling_volume(), 1_000);
+
+        let mut usage = test_usage(START_HOUR);
+        assert!(usage.record_purchase(START_HOUR, 1_001, 1_000).is_err());
+        assert_eq!(usage.rolling_volume(), 0);
+
+        // Once the first purchase expires there is room again
+        let mut usage = test_usage(START_HOUR);
+        usage.record_purchase(START_HOUR, 1_000, 1_000).unwrap();
+        assert!(usage.record_purchase(START_HOUR + 23, 1, 1_000).is_err());
+        assert_eq!(usage.record_purchase(START_HOUR + 24, 1_000, 1_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn single_transfer_limit_is_inclusive() {
+        let limits = MintLimits { mint: Pubkey::new_unique(), max_transfer_amount: 500, max_daily_volume: 0, bump: 255 };
+        assert!(limits.check_transfer_amount(499).is_ok());
+        assert!(limits.check_transfer_amount(500).is_ok());
+        assert!(limits.check_transfer_amount(501).is_err());
+
+        let unlimited = MintLimits { max_transfer_amount: 0, ..limits };
+        assert!(unlimited.check_transfer_amount(u64::MAX).is_ok());
+    }
+}
@@ -0,0 +1,104 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { BPF_LOADER_UPGRADEABLE_PROGRAM_ID, Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+
+// Magnitudes are integers in hundredths: 700 = M7.00
+const TIMELOCK_DELAY_SECONDS = 1;
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+describe("earthquake insurance fixed-point magnitudes", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.EarthquakeInsuranceHook as Program<any>;
+  const authority = provider.wallet.publicKey;
+
+  const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+  // `anchor test` deploys with the provider wallet as upgrade authority
+  const [programData] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
+  const feed = Keypair.generate().publicKey;
+  let nextZoneId = 1;
+
+  const zonePda = (zoneId: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("zone"), new BN(zoneId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  const changePda = (changeId: BN) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("change"), changeId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+  // Zones are created through the timelock: propose, wait out the delay, execute
+  const createZone = async (thresholdMagnitude: number) => {
+    const zoneId = nextZoneId++;
+    const changeId = (await program.account.insuranceConfig.fetch(configPda)).changeCount;
+    await program.methods
+      .proposeChange({
+        newCoverageZone: {
+          zoneId: new BN(zoneId),
+          feed,
+          thresholdMagnitude: new BN(thresholdMagnitude),
+          premiumRateBps: 100,
+          waitingPeriodSeconds: new BN(0),
+          gracePeriodSeconds: new BN(0),
+        },
+      })
+      .accounts({ config: configPda, pendingChange: changePda(changeId), authority })
+      .rpc();
+
+    await sleep((TIMELOCK_DELAY_SECONDS + 1) * 1000);
+    await program.methods
+      .createCoverageZone(changeId, new BN(zoneId))
+      .accounts({
+        zone: zonePda(zoneId),
+        pendingChange: changePda(changeId),
+        proposer: authority,
+        executor: authority,
+      })
+      .rpc();
+    return program.account.coverageZone.fetch(zonePda(zoneId));
+  };
+
+  const expectRejected = async (threshold: number) => {
+    try {
+      await createZone(threshold);
+    } catch (err: any) {
+      expect(err.error?.errorCode?.code).to.equal("InvalidMagnitude");
+      return;
+    }
+    expect.fail("zone with an invalid threshold was created");
+  };
+
+  before(async () => {
+    await program.methods
+      .initializeConfig(
+        new BN(300),
+        500,
+        new BN(3600),
+        new BN(1_000_000),
+        authority,
+        authority,
+        new BN(TIMELOCK_DELAY_SECONDS),
+        false
+      )
+      .accounts({ authority, program: program.programId, programData })
+      .rpc();
+  });
+
+  it("accepts the exact range bounds", async () => {
+    expect((await createZone(200)).thresholdMagnitude.toNumber()).to.equal(200);
+    expect((await createZone(900)).thresholdMagnitude.toNumber()).to.equal(900);
+  });
+
+  it("rejects values just outside the range when proposed", async () => {
+    await expectRejected(199);
+    await expectRejected(901);
+  });
+});